@@ -0,0 +1,356 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::db::operations::EntryRecord;
+
+/// Длина SHA-1 в шестнадцатеричной записи
+const HASH_LEN: usize = 40;
+/// Длина префикса хеша в имени файла диапазона
+const PREFIX_LEN: usize = 5;
+
+/// Источник сведений об утечках: число появлений пароля в утечках по его SHA-1
+pub trait BreachSource {
+    /// `hash` — SHA-1 пароля, 40 шестнадцатеричных символов в верхнем регистре
+    fn count(&mut self, hash: &str) -> Result<u64>;
+}
+
+/// Запись, пароль которой найден в утечках
+#[derive(Debug, Serialize)]
+pub struct BreachedEntry {
+    pub id: u64,
+    /// Сколько раз пароль встречался в утечках
+    pub count: u64,
+}
+
+/// Результат проверки паролей хранилища по утечкам
+#[derive(Debug, Serialize)]
+pub struct BreachReport {
+    /// Сколько записей с непустым паролем проверено
+    pub checked: usize,
+    pub breached: Vec<BreachedEntry>,
+}
+
+/// Проверяет пароли записей по источнику. Одинаковые пароли проверяются один раз.
+pub fn check(
+    records: &[(u64, EntryRecord)],
+    source: &mut dyn BreachSource,
+) -> Result<BreachReport> {
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    let mut checked = 0;
+    let mut breached = Vec::new();
+
+    for (id, record) in records {
+        if record.password.is_empty() {
+            continue;
+        }
+        checked += 1;
+
+        let hash = sha1_hex(&record.password);
+        let count = match counts.get(&hash) {
+            Some(count) => *count,
+            None => {
+                let count = source.count(&hash)?;
+                counts.insert(hash, count);
+                count
+            }
+        };
+
+        if count > 0 {
+            breached.push(BreachedEntry { id: *id, count });
+        }
+    }
+
+    breached.sort_by(|a, b| b.count.cmp(&a.count).then(a.id.cmp(&b.id)));
+    Ok(BreachReport { checked, breached })
+}
+
+/// SHA-1 пароля в верхнем регистре, как в списках Pwned Passwords
+pub fn sha1_hex(password: &str) -> String {
+    Sha1::digest(password.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect()
+}
+
+/// Локальный список Pwned Passwords одного из двух видов:
+/// - один текстовый файл строк `SHA1:COUNT`, отсортированный по хешу
+///   (вариант «ordered by hash»);
+/// - папка файлов диапазонов `XXXXX` или `XXXXX.txt` со строками
+///   `SUFFIX:COUNT` — без первых пяти символов хеша, как их отдаёт сервис
+///   диапазонов.
+///
+/// Общий файл занимает десятки гигабайт, поэтому не читается целиком: каждый
+/// хеш ищется двоичным поиском по смещениям в файле. В папке открывается только
+/// файл диапазона искомого хеша, и суффикс ищется в нём так же.
+pub struct HashList {
+    kind: ListKind,
+}
+
+enum ListKind {
+    File(SortedFile),
+    Ranges(PathBuf),
+}
+
+impl HashList {
+    /// Открывает список и проверяет, что он похож на отсортированный список SHA-1.
+    /// У папки проверяется первый файл диапазонов, `00000`.
+    pub fn open(path: &Path) -> Result<Self> {
+        if !path.is_dir() {
+            return Ok(Self {
+                kind: ListKind::File(SortedFile::open(path, HASH_LEN)?),
+            });
+        }
+
+        let Some(first) = range_file(path, &"0".repeat(PREFIX_LEN)) else {
+            anyhow::bail!("Not a Pwned Passwords range directory");
+        };
+        SortedFile::open(&first, HASH_LEN - PREFIX_LEN)?;
+
+        Ok(Self {
+            kind: ListKind::Ranges(path.to_path_buf()),
+        })
+    }
+}
+
+impl BreachSource for HashList {
+    fn count(&mut self, hash: &str) -> Result<u64> {
+        let hash = hash.to_ascii_uppercase();
+
+        match &mut self.kind {
+            ListKind::File(file) => file.count(&hash),
+            ListKind::Ranges(dir) => {
+                let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+                let path = range_file(dir, prefix)
+                    .with_context(|| format!("Range file {prefix} is missing"))?;
+                SortedFile::open(&path, HASH_LEN - PREFIX_LEN)?.count(suffix)
+            }
+        }
+    }
+}
+
+/// Файл диапазона в папке: `XXXXX` или `XXXXX.txt`
+fn range_file(dir: &Path, prefix: &str) -> Option<PathBuf> {
+    [dir.join(prefix), dir.join(format!("{prefix}.txt"))]
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+/// Отсортированный файл строк `KEY:COUNT` с ключами длиной `key_len`
+struct SortedFile {
+    reader: BufReader<File>,
+    len: u64,
+    key_len: usize,
+}
+
+impl SortedFile {
+    fn open(path: &Path, key_len: usize) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open hash list: {:?}", path))?;
+        let len = file
+            .metadata()
+            .context("Failed to read hash list size")?
+            .len();
+
+        let mut list = Self {
+            reader: BufReader::new(file),
+            len,
+            key_len,
+        };
+
+        let first = list.line_at(0)?;
+        let Some((first, _)) = first.as_deref().and_then(|line| parse_line(line, key_len)) else {
+            anyhow::bail!("Not a Pwned Passwords SHA-1 hash list");
+        };
+        let second = list.next_line()?;
+        if let Some((second, _)) = second.as_deref().and_then(|line| parse_line(line, key_len)) {
+            if second < first {
+                anyhow::bail!("Hash list must be sorted by hash");
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Сколько раз встречается ключ; `key` — в верхнем регистре
+    fn count(&mut self, key: &str) -> Result<u64> {
+        // Ищем наименьшее смещение, с которого первая строка не меньше искомой
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let found = self.line_at(middle)?;
+            match found
+                .as_deref()
+                .and_then(|line| parse_line(line, self.key_len))
+            {
+                Some((line_key, _)) if line_key.as_str() < key => low = middle + 1,
+                _ => high = middle,
+            }
+        }
+
+        let found = self.line_at(low)?;
+        Ok(
+            match found
+                .as_deref()
+                .and_then(|line| parse_line(line, self.key_len))
+            {
+                Some((line_key, count)) if line_key == key => count,
+                _ => 0,
+            },
+        )
+    }
+
+    /// Первая целая строка, которая начинается на смещении `offset` или после него
+    fn line_at(&mut self, offset: u64) -> Result<Option<String>> {
+        if offset == 0 {
+            self.reader.seek(SeekFrom::Start(0))?;
+        } else {
+            // Дочитываем строку, в которую попало смещение
+            self.reader.seek(SeekFrom::Start(offset - 1))?;
+            self.reader.read_until(b'\n', &mut Vec::new())?;
+        }
+
+        self.next_line()
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end().to_string()))
+    }
+}
+
+/// Разбирает строку `KEY:COUNT` с шестнадцатеричным ключом длиной `key_len`;
+/// без счётчика пароль считается встреченным один раз
+fn parse_line(line: &str, key_len: usize) -> Option<(String, u64)> {
+    let (key, count) = line.split_once(':').unwrap_or((line, "1"));
+    if key.len() != key_len || !key.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some((key.to_ascii_uppercase(), count.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fs::TestDir;
+
+    const FIRST: &str = "000000005AD76BD555C1D6D771DE417A4B87E4B4";
+    const MIDDLE: &str = "7C4A8D09CA3762AF61E59520943DC26494F8941B";
+    const LAST: &str = "FFFFFFFEE791CBAC0F6305CAF0CEE06BBE131160";
+    /// Хеш между MIDDLE и LAST, которого нет в списке
+    const MISSING: &str = "A94A8FE5CCB19BA61C4C0873D391E987982FBBD3";
+
+    fn hash_list(dir: &TestDir, line_end: &str) -> HashList {
+        let path = dir.join("pwned.txt");
+        let body = [
+            format!("{FIRST}:4"),
+            format!("{MIDDLE}:24230577"),
+            format!("{LAST}:2"),
+        ]
+        .join(line_end);
+        std::fs::write(&path, body + line_end).unwrap();
+        HashList::open(&path).unwrap()
+    }
+
+    #[test]
+    fn hashes_are_found_at_both_ends_and_in_the_middle() {
+        let dir = TestDir::new("breach-lf");
+        let mut list = hash_list(&dir, "\n");
+
+        assert_eq!(list.count(FIRST).unwrap(), 4);
+        assert_eq!(list.count(MIDDLE).unwrap(), 24230577);
+        assert_eq!(list.count(LAST).unwrap(), 2);
+        assert_eq!(list.count(MISSING).unwrap(), 0);
+        assert_eq!(list.count(&"0".repeat(HASH_LEN)).unwrap(), 0);
+        assert_eq!(list.count(&"F".repeat(HASH_LEN)).unwrap(), 0);
+    }
+
+    #[test]
+    fn crlf_lines_are_read() {
+        let dir = TestDir::new("breach-crlf");
+        let mut list = hash_list(&dir, "\r\n");
+
+        assert_eq!(list.count(FIRST).unwrap(), 4);
+        assert_eq!(list.count(LAST).unwrap(), 2);
+        assert_eq!(list.count(MISSING).unwrap(), 0);
+    }
+
+    #[test]
+    fn lowercase_hash_is_found() {
+        let dir = TestDir::new("breach-lowercase");
+        let mut list = hash_list(&dir, "\n");
+
+        assert_eq!(list.count(&MIDDLE.to_ascii_lowercase()).unwrap(), 24230577);
+    }
+
+    #[test]
+    fn hashes_are_found_in_range_files() {
+        let dir = TestDir::new("breach-ranges");
+        let ranges = dir.join("ranges");
+        std::fs::create_dir(&ranges).unwrap();
+        let line = |hash: &str, count: u64| format!("{}:{count}\r\n", &hash[PREFIX_LEN..]);
+        std::fs::write(ranges.join("00000.txt"), line(FIRST, 4)).unwrap();
+        // Фиктивная запись с нулевым счётчиком, как в дополненных ответах
+        let padded = line("7C4A80000000000000000000000000000000000A", 0) + &line(MIDDLE, 24230577);
+        std::fs::write(ranges.join("7C4A8"), padded).unwrap();
+        std::fs::write(
+            ranges.join("A94A8"),
+            line("A94A8FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF", 1),
+        )
+        .unwrap();
+
+        let mut list = HashList::open(&ranges).unwrap();
+
+        assert_eq!(list.count(FIRST).unwrap(), 4);
+        assert_eq!(list.count(MIDDLE).unwrap(), 24230577);
+        assert_eq!(list.count(MISSING).unwrap(), 0);
+        // Файла диапазона нет — список неполный, а не пароль чистый
+        assert!(list.count(LAST).is_err());
+    }
+
+    #[test]
+    fn unsorted_or_foreign_files_are_rejected() {
+        let dir = TestDir::new("breach-invalid");
+
+        std::fs::write(dir.join("unsorted.txt"), format!("{LAST}:2\n{FIRST}:4\n")).unwrap();
+        assert!(HashList::open(&dir.join("unsorted.txt")).is_err());
+
+        std::fs::write(dir.join("other.txt"), "site,login,password\n").unwrap();
+        assert!(HashList::open(&dir.join("other.txt")).is_err());
+
+        // Папка без файлов диапазонов
+        std::fs::create_dir(dir.join("empty")).unwrap();
+        assert!(HashList::open(&dir.join("empty")).is_err());
+    }
+
+    #[test]
+    fn every_entry_with_a_breached_password_is_reported() {
+        let dir = TestDir::new("breach-check");
+        let mut list = hash_list(&dir, "\n");
+        let record = |password: &str| EntryRecord {
+            password: password.to_string(),
+            ..Default::default()
+        };
+        // SHA-1 «123456» — MIDDLE
+        let records = vec![
+            (1, record("123456")),
+            (2, record("")),
+            (3, record("123456")),
+            (4, record("не утёк")),
+        ];
+
+        let report = check(&records, &mut list).unwrap();
+
+        assert_eq!(report.checked, 3);
+        let ids: Vec<u64> = report.breached.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+    }
+}
@@ -0,0 +1,315 @@
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+
+use crate::db::operations::{EntryRecord, EntryType};
+use crate::import::url_host;
+use crate::utils::strength;
+
+/// Пароль с оценкой ниже считается слабым
+const MIN_STRONG_SCORE: u8 = 3;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Штрафы записи за каждую проблему; сумма не превышает 100
+const WEAK_PENALTY: u32 = 40;
+const REUSED_PENALTY: u32 = 30;
+const INSECURE_URL_PENALTY: u32 = 10;
+const OLD_PENALTY: u32 = 10;
+const MISSING_TOTP_PENALTY: u32 = 10;
+
+/// Отчёт о состоянии паролей записей-логинов. Содержит только ID записей,
+/// сами пароли в отчёт не попадают.
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub total: usize,
+    pub weak: Vec<u64>,
+    pub reused: Vec<ReusedGroup>,
+    pub old: Vec<u64>,
+    pub missing_totp: Vec<u64>,
+    pub insecure_urls: Vec<u64>,
+    /// Общая оценка от 0 до 100
+    pub score: u8,
+}
+
+/// Записи с одинаковым паролем
+#[derive(Debug, Serialize)]
+pub struct ReusedGroup {
+    /// Метка группы: HMAC пароля на случайном ключе, свежем для каждого отчёта,
+    /// поэтому по ней нельзя подобрать пароль или сравнить отчёты между собой
+    pub hash: String,
+    pub ids: Vec<u64>,
+}
+
+/// Проверяет записи: слабые, повторяющиеся и давно не менявшиеся пароли,
+/// отсутствие TOTP и адреса без HTTPS
+pub fn report(records: &[(u64, EntryRecord)], max_age_days: u32, now: i64) -> HealthReport {
+    let max_age = i64::from(max_age_days) * SECONDS_PER_DAY;
+    let key: [u8; 32] = rand::random();
+
+    let mut weak = Vec::new();
+    let mut old = Vec::new();
+    let mut missing_totp = Vec::new();
+    let mut insecure_urls = Vec::new();
+    let mut by_password: HashMap<String, Vec<u64>> = HashMap::new();
+    let mut penalties: HashMap<u64, u32> = HashMap::new();
+
+    // Пароли, TOTP и адреса есть только у логинов
+    let logins: Vec<&(u64, EntryRecord)> = records
+        .iter()
+        .filter(|(_, record)| record.entry_type == EntryType::Login)
+        .collect();
+
+    for (id, record) in logins.iter().copied() {
+        let mut penalty = 0;
+
+        if !record.password.is_empty() {
+            let inputs = [record.site.as_str(), record.login.as_str()];
+            if strength::estimate(&record.password, &inputs).score < MIN_STRONG_SCORE {
+                weak.push(*id);
+                penalty += WEAK_PENALTY;
+            }
+            by_password
+                .entry(password_tag(&key, &record.password))
+                .or_default()
+                .push(*id);
+        }
+
+        let changed_at = password_changed_at(record);
+        if max_age > 0 && changed_at > 0 && now - changed_at > max_age {
+            old.push(*id);
+            penalty += OLD_PENALTY;
+        }
+
+        if record
+            .totp
+            .as_deref()
+            .is_none_or(|totp| totp.trim().is_empty())
+        {
+            missing_totp.push(*id);
+            penalty += MISSING_TOTP_PENALTY;
+        }
+
+        if record.url.as_deref().is_some_and(is_insecure_url) {
+            insecure_urls.push(*id);
+            penalty += INSECURE_URL_PENALTY;
+        }
+
+        penalties.insert(*id, penalty);
+    }
+
+    let mut reused: Vec<ReusedGroup> = by_password
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(hash, ids)| ReusedGroup { hash, ids })
+        .collect();
+    reused.sort_by(|a, b| b.ids.len().cmp(&a.ids.len()).then(a.ids.cmp(&b.ids)));
+    for id in reused.iter().flat_map(|group| &group.ids) {
+        *penalties.entry(*id).or_default() += REUSED_PENALTY;
+    }
+
+    let score = if logins.is_empty() {
+        100
+    } else {
+        let total_penalty: u32 = penalties.values().map(|penalty| (*penalty).min(100)).sum();
+        100 - (total_penalty / logins.len() as u32) as u8
+    };
+
+    HealthReport {
+        total: logins.len(),
+        weak,
+        reused,
+        old,
+        missing_totp,
+        insecure_urls,
+        score,
+    }
+}
+
+/// Когда пароль был установлен: время последней замены из истории,
+/// иначе время создания записи; 0 — неизвестно
+fn password_changed_at(record: &EntryRecord) -> i64 {
+    record
+        .history
+        .iter()
+        .map(|item| item.changed_at)
+        .max()
+        .filter(|changed_at| *changed_at > 0)
+        .unwrap_or(record.created_at)
+}
+
+/// Адрес с явной схемой `http://`, кроме адресов этого компьютера
+fn is_insecure_url(url: &str) -> bool {
+    let url = url.trim();
+    let Some((scheme, _)) = url.split_once("://") else {
+        return false;
+    };
+    if !scheme.eq_ignore_ascii_case("http") {
+        return false;
+    }
+
+    !matches!(
+        url_host(url).as_deref(),
+        Some("localhost" | "127.0.0.1" | "::1")
+    )
+}
+
+fn password_tag(key: &[u8], password: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(password.as_bytes());
+    mac.finalize().into_bytes()[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::operations::PasswordHistoryItem;
+
+    /// 2024-05-20 12:00:00 UTC
+    const NOW: i64 = 1_716_206_400;
+    const STRONG: &str = "vivid-Otter-harbor-91-lantern";
+
+    /// Логин без проблем: стойкий пароль, TOTP, HTTPS, создан вчера
+    fn login(id: u64, password: &str) -> (u64, EntryRecord) {
+        (
+            id,
+            EntryRecord {
+                site: format!("site{id}"),
+                login: "octocat".to_string(),
+                password: password.to_string(),
+                url: Some(format!("https://site{id}.example")),
+                totp: Some("JBSWY3DPEHPK3PXP".to_string()),
+                created_at: NOW - SECONDS_PER_DAY,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn clean_logins_score_100() {
+        let report = report(
+            &[
+                login(1, STRONG),
+                login(2, "another-Sturdy-kettle-58-meadow"),
+            ],
+            180,
+            NOW,
+        );
+
+        assert_eq!(report.total, 2);
+        assert!(report.weak.is_empty());
+        assert!(report.reused.is_empty());
+        assert!(report.old.is_empty());
+        assert!(report.missing_totp.is_empty());
+        assert!(report.insecure_urls.is_empty());
+        assert_eq!(report.score, 100);
+        assert_eq!(super::report(&[], 180, NOW).score, 100);
+    }
+
+    #[test]
+    fn problems_are_grouped_by_kind() {
+        let mut no_totp = login(3, "third-Brisk-walnut-27-compass");
+        no_totp.1.totp = Some("  ".to_string());
+        let mut old = login(4, "fourth-Quiet-pepper-63-saddle");
+        old.1.created_at = NOW - 400 * SECONDS_PER_DAY;
+        let mut insecure = login(5, "fifth-Amber-violin-14-thistle");
+        insecure.1.url = Some("http://site5.example/login".to_string());
+        let note = (
+            6,
+            EntryRecord {
+                entry_type: EntryType::SecureNote,
+                site: "Заметка".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let records = [
+            login(1, "123456"),
+            login(2, STRONG),
+            no_totp,
+            old,
+            insecure,
+            note,
+            login(7, STRONG),
+        ];
+        let report = report(&records, 180, NOW);
+
+        // Заметка не логин и в отчёт не попадает
+        assert_eq!(report.total, 6);
+        assert_eq!(report.weak, vec![1]);
+        assert_eq!(report.reused.len(), 1);
+        assert_eq!(report.reused[0].ids, vec![2, 7]);
+        assert_eq!(report.old, vec![4]);
+        assert_eq!(report.missing_totp, vec![3]);
+        assert_eq!(report.insecure_urls, vec![5]);
+    }
+
+    #[test]
+    fn score_averages_penalties_per_login() {
+        let mut weak = login(1, "password");
+        weak.1.totp = None;
+
+        let report = report(&[weak, login(2, STRONG)], 180, NOW);
+
+        // (40 + 10) / 2 записи
+        assert_eq!(report.score, 75);
+    }
+
+    #[test]
+    fn reused_tag_is_not_a_plain_password_hash() {
+        let report = report(&[login(1, STRONG), login(2, STRONG)], 180, NOW);
+
+        assert_eq!(report.reused[0].hash.len(), 16);
+        assert_ne!(
+            report.reused[0].hash,
+            crate::audit::breach::sha1_hex(STRONG)
+        );
+    }
+
+    #[test]
+    fn password_age_prefers_history_over_creation_time() {
+        let (_, mut record) = login(1, STRONG);
+        record.created_at = 1_000;
+        record.updated_at = 9_000;
+        assert_eq!(password_changed_at(&record), 1_000);
+
+        record.history = vec![
+            PasswordHistoryItem {
+                password: "old".to_string(),
+                changed_at: 5_000,
+            },
+            PasswordHistoryItem {
+                password: "older".to_string(),
+                changed_at: 3_000,
+            },
+        ];
+        assert_eq!(password_changed_at(&record), 5_000);
+
+        // Неизвестное время не делает пароль старым
+        let (id, mut unknown) = login(1, STRONG);
+        unknown.created_at = 0;
+        assert!(report(&[(id, unknown)], 1, NOW).old.is_empty());
+        // Без срока давности старых паролей нет
+        let (id, mut ancient) = login(1, STRONG);
+        ancient.created_at = 1;
+        assert!(report(&[(id, ancient)], 0, NOW).old.is_empty());
+    }
+
+    #[test]
+    fn only_plain_http_to_other_hosts_is_insecure() {
+        for (url, insecure) in [
+            ("http://example.com", true),
+            ("HTTP://Example.com/login", true),
+            ("https://example.com", false),
+            ("http://localhost:8080", false),
+            ("http://127.0.0.1/admin", false),
+            ("example.com", false),
+            ("ftp://example.com", false),
+        ] {
+            assert_eq!(is_insecure_url(url), insecure, "{url}");
+        }
+    }
+}
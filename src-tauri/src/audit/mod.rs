@@ -0,0 +1,3 @@
+pub mod breach;
+pub mod health;
+pub mod range_api;
@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::breach::BreachSource;
+
+/// Длина префикса SHA-1, который уходит в сеть
+const PREFIX_LEN: usize = 5;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Ответы сервиса по адресу диапазона: окончания хешей и число утечек.
+/// Хранятся только в памяти, чтобы на диске не оставалось префиксов паролей.
+#[derive(Default)]
+pub struct RangeCache {
+    ranges: HashMap<String, (Instant, HashMap<String, u64>)>,
+}
+
+impl RangeCache {
+    fn get(&self, url: &str, ttl: Duration) -> Option<&HashMap<String, u64>> {
+        self.ranges
+            .get(url)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < ttl)
+            .map(|(_, suffixes)| suffixes)
+    }
+
+    /// Сохраняет ответ и заодно забывает устаревшие. При нулевом сроке
+    /// ответ не сохраняется: он всё равно не был бы использован.
+    fn insert(&mut self, url: String, suffixes: HashMap<String, u64>, ttl: Duration) {
+        self.ranges
+            .retain(|_, (fetched_at, _)| fetched_at.elapsed() < ttl);
+        if !ttl.is_zero() {
+            self.ranges.insert(url, (Instant::now(), suffixes));
+        }
+    }
+
+    /// Забывает все сохранённые ответы
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+}
+
+/// Проверка по сети с k-анонимностью: сервису отправляются только первые
+/// пять символов SHA-1, а совпадение ищется в полученном списке окончаний
+pub struct RangeApi {
+    client: reqwest::blocking::Client,
+    base_url: String,
+    padding: bool,
+    ttl: Duration,
+    cache: Arc<Mutex<RangeCache>>,
+}
+
+impl RangeApi {
+    /// `base_url` — адрес сервиса или локального зеркала без `/range`;
+    /// `padding` — просить сервис дополнять ответ фиктивными записями,
+    /// чтобы по размеру ответа нельзя было узнать префикс
+    pub fn new(
+        base_url: &str,
+        padding: bool,
+        ttl: Duration,
+        cache: Arc<Mutex<RangeCache>>,
+    ) -> Result<Self> {
+        let base_url = normalize_base_url(base_url)?;
+        let client = reqwest::blocking::Client::builder()
+            .user_agent(concat!("nopeekpanda/", env!("CARGO_PKG_VERSION")))
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            base_url,
+            padding,
+            ttl,
+            cache,
+        })
+    }
+
+    fn fetch(&self, url: &str) -> Result<HashMap<String, u64>> {
+        let mut request = self.client.get(url);
+        if self.padding {
+            request = request.header("Add-Padding", "true");
+        }
+
+        let body = request
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .with_context(|| format!("Range request failed: {url}"))?;
+
+        Ok(parse_range(&body))
+    }
+}
+
+impl BreachSource for RangeApi {
+    fn count(&mut self, hash: &str) -> Result<u64> {
+        let hash = hash.to_ascii_uppercase();
+        let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+        let url = format!("{}/range/{}", self.base_url, prefix);
+
+        let cache = self
+            .cache
+            .lock()
+            .expect("Failed to acquire breach cache lock");
+        if let Some(suffixes) = cache.get(&url, self.ttl) {
+            return Ok(suffixes.get(suffix).copied().unwrap_or(0));
+        }
+        // Запрос может идти долго — другие проверки не должны его ждать
+        drop(cache);
+
+        let suffixes = self.fetch(&url)?;
+        let count = suffixes.get(suffix).copied().unwrap_or(0);
+        self.cache
+            .lock()
+            .expect("Failed to acquire breach cache lock")
+            .insert(url, suffixes, self.ttl);
+
+        Ok(count)
+    }
+}
+
+/// Проверяет адрес сервиса и убирает завершающие `/`. Без TLS разрешено
+/// обращаться только к локальному зеркалу на этом компьютере.
+pub fn normalize_base_url(base_url: &str) -> Result<String> {
+    let base_url = base_url.trim().trim_end_matches('/');
+    let Some((scheme, rest)) = base_url.split_once("://") else {
+        anyhow::bail!("Range API URL must start with https://");
+    };
+    if rest.is_empty() || rest.contains(['?', '#', '@']) {
+        anyhow::bail!("Invalid range API URL: {base_url}");
+    }
+
+    let host = rest.split('/').next().unwrap_or_default();
+    let secure = scheme.eq_ignore_ascii_case("https");
+    let local = scheme.eq_ignore_ascii_case("http") && is_loopback(host);
+    if !secure && !local {
+        anyhow::bail!("Range API URL must use https:// unless it points to this computer");
+    }
+
+    Ok(base_url.to_string())
+}
+
+/// Указывает ли хост (возможно, с портом) на этот компьютер
+fn is_loopback(host: &str) -> bool {
+    let host = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|address| address.is_loopback())
+}
+
+/// Разбирает ответ `SUFFIX:COUNT` по строкам. Записи дополнения имеют
+/// нулевой счётчик и пропускаются.
+fn parse_range(body: &str) -> HashMap<String, u64> {
+    body.lines()
+        .filter_map(|line| {
+            let (suffix, count) = line.trim().split_once(':')?;
+            let count: u64 = count.trim().parse().ok()?;
+            (count > 0).then(|| (suffix.to_ascii_uppercase(), count))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_lines_are_parsed_without_padding() {
+        let body = "0018A45C4D1DEF81644B54AB7F969B88D65:3\r\n\
+                    00D4F6E8FA6EECAD2A3AA415EEC418D38EC:0\r\n\
+                    011053FD0102E94D6AE2F8B83D76FAF94f6:12\r\n\
+                    broken line\r\n";
+
+        let suffixes = parse_range(body);
+
+        assert_eq!(suffixes.len(), 2);
+        assert_eq!(suffixes["0018A45C4D1DEF81644B54AB7F969B88D65"], 3);
+        assert_eq!(suffixes["011053FD0102E94D6AE2F8B83D76FAF94F6"], 12);
+        assert!(!suffixes.contains_key("00D4F6E8FA6EECAD2A3AA415EEC418D38EC"));
+    }
+
+    #[test]
+    fn base_url_loses_trailing_slashes() {
+        assert_eq!(
+            normalize_base_url(" https://api.pwnedpasswords.com// ").unwrap(),
+            "https://api.pwnedpasswords.com"
+        );
+        assert_eq!(
+            normalize_base_url("https://mirror.example/hibp/").unwrap(),
+            "https://mirror.example/hibp"
+        );
+    }
+
+    #[test]
+    fn plain_http_is_allowed_only_for_this_computer() {
+        for url in [
+            "http://localhost:8080",
+            "http://127.0.0.1/hibp",
+            "http://[::1]:8080/",
+        ] {
+            assert!(normalize_base_url(url).is_ok(), "{url}");
+        }
+
+        for url in [
+            "http://mirror.example",
+            "http://localhost.example",
+            "http://10.0.0.5:8080",
+            "ftp://localhost",
+            "api.pwnedpasswords.com",
+            "https://",
+            "https://api.pwnedpasswords.com/?q=1",
+            "http://user@localhost",
+        ] {
+            assert!(normalize_base_url(url).is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn cache_forgets_expired_ranges_and_skips_zero_ttl() {
+        let mut cache = RangeCache::default();
+        let ttl = Duration::from_secs(3600);
+
+        cache.insert("a".to_string(), HashMap::new(), Duration::ZERO);
+        assert!(cache.ranges.is_empty());
+
+        cache.insert("a".to_string(), HashMap::new(), ttl);
+        assert!(cache.get("a", ttl).is_some());
+
+        // С меньшим сроком прежний ответ уже устарел и удаляется при вставке
+        std::thread::sleep(Duration::from_millis(5));
+        cache.insert("b".to_string(), HashMap::new(), Duration::from_millis(1));
+        assert!(!cache.ranges.contains_key("a"));
+        assert!(cache.ranges.contains_key("b"));
+    }
+}
@@ -0,0 +1,317 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, MAIN_DB};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::operations::unix_now;
+use crate::utils::fs;
+
+/// Наибольший размер одного вложения. Проверяется и при добавлении файла,
+/// и при записи вложений из импорта, восстановления или другого хранилища.
+///
+/// Потоком вложения идут только при добавлении и сохранении в файл. Экспорт
+/// хранилища (зашифрованный JSON, KDBX) и копирование записей между хранилищами
+/// читают содержимое всех вложений переносимых записей в память, и предел
+/// одного вложения их общий расход памяти не ограничивает.
+pub const MAX_ATTACHMENT_SIZE: u64 = 64 * 1024 * 1024;
+/// Максимальная длина имени вложения в символах
+const MAX_NAME_LEN: usize = 255;
+
+/// Вложение без содержимого — для списка в интерфейсе
+#[derive(Debug, Clone, Serialize)]
+pub struct AttachmentInfo {
+    pub id: u64,
+    pub entry_id: u64,
+    pub name: String,
+    /// Размер в байтах
+    pub size: u64,
+    /// Время добавления, секунды Unix
+    pub created_at: i64,
+}
+
+/// Вложения записи без содержимого, в порядке добавления
+pub fn list(conn: &Connection, entry_id: u64) -> Result<Vec<AttachmentInfo>> {
+    ensure_entry(conn, entry_id)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, entry_id, name, size, created_at FROM attachments
+             WHERE entry_id = ?1 ORDER BY id",
+        )
+        .context("Failed to prepare attachment list query")?;
+
+    let rows = stmt
+        .query_map(params![entry_id], attachment_info_from_row)
+        .context("Failed to execute attachment list query")?;
+
+    let mut attachments = Vec::new();
+    for row in rows {
+        attachments.push(row.context("Failed to parse attachment")?);
+    }
+
+    Ok(attachments)
+}
+
+/// Сведения о вложении по ID
+pub fn get(conn: &Connection, id: u64) -> Result<AttachmentInfo> {
+    conn.query_row(
+        "SELECT id, entry_id, name, size, created_at FROM attachments WHERE id = ?1",
+        params![id],
+        attachment_info_from_row,
+    )
+    .optional()
+    .context("Failed to query attachment")?
+    .ok_or_else(|| anyhow::anyhow!("Attachment {} not found", id))
+}
+
+/// Добавляет файл к записи. Содержимое пишется в БД потоком через
+/// инкрементальный BLOB-ввод/вывод SQLite, без загрузки файла в память целиком.
+pub fn attach(conn: &Connection, entry_id: u64, source: &Path) -> Result<AttachmentInfo> {
+    ensure_entry(conn, entry_id)?;
+
+    let name = source
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Invalid attachment file name")?
+        .to_string();
+    if name.chars().count() > MAX_NAME_LEN {
+        anyhow::bail!("Attachment name is longer than {} characters", MAX_NAME_LEN);
+    }
+
+    let file =
+        File::open(source).with_context(|| format!("Failed to open attachment: {:?}", source))?;
+    let size = file
+        .metadata()
+        .context("Failed to read attachment size")?
+        .len();
+    ensure_size(size)?;
+
+    let created_at = unix_now();
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+
+    // Место под содержимое резервируется заранее: BLOB нельзя увеличить при записи
+    tx.execute(
+        "INSERT INTO attachments (entry_id, name, size, data, created_at)
+         VALUES (?1, ?2, ?3, zeroblob(?3), ?4)",
+        params![entry_id, name, size as i64, created_at],
+    )
+    .context("Failed to insert attachment")?;
+    let id = tx.last_insert_rowid();
+
+    let mut blob = tx
+        .blob_open(MAIN_DB, "attachments", "data", id, false)
+        .context("Failed to open attachment blob")?;
+    let written = std::io::copy(&mut BufReader::new(file).take(size), &mut blob)
+        .context("Failed to write attachment")?;
+    // Файл мог уменьшиться, пока его читали
+    if written != size {
+        anyhow::bail!("Attachment file changed while it was being read");
+    }
+    blob.close().context("Failed to write attachment")?;
+
+    touch_entry(&tx, entry_id)?;
+    tx.commit().context("Failed to commit transaction")?;
+
+    Ok(AttachmentInfo {
+        id: id as u64,
+        entry_id,
+        name,
+        size,
+        created_at,
+    })
+}
+
+/// Проверяет, что вложение не больше `MAX_ATTACHMENT_SIZE`
+pub(crate) fn ensure_size(size: u64) -> Result<()> {
+    if size > MAX_ATTACHMENT_SIZE {
+        anyhow::bail!(
+            "Attachment is larger than {} MiB",
+            MAX_ATTACHMENT_SIZE / 1024 / 1024
+        );
+    }
+    Ok(())
+}
+
+/// Сохраняет вложение в файл, читая его из БД частями
+pub fn export(conn: &Connection, id: u64, destination: &Path) -> Result<()> {
+    get(conn, id)?;
+
+    let mut blob = conn
+        .blob_open(MAIN_DB, "attachments", "data", id as i64, true)
+        .context("Failed to open attachment blob")?;
+
+    write_file(&mut blob, destination)
+}
+
+/// Пишет содержимое в новый файл только для владельца; при ошибке файл удаляется
+fn write_file(source: &mut impl Read, destination: &Path) -> Result<()> {
+    let mut file = BufWriter::new(fs::create_private_file(destination)?);
+    let result = std::io::copy(source, &mut file).and_then(|_| file.flush());
+    if let Err(e) = result {
+        // Не оставляем неполный файл
+        drop(file);
+        let _ = std::fs::remove_file(destination);
+        return Err(e).context("Failed to export attachment");
+    }
+
+    Ok(())
+}
+
+/// Удаляет вложение
+pub fn delete(conn: &Connection, id: u64) -> Result<()> {
+    let attachment = get(conn, id)?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+    tx.execute("DELETE FROM attachments WHERE id = ?1", params![id])
+        .context("Failed to delete attachment")?;
+    touch_entry(&tx, attachment.entry_id)?;
+    tx.commit().context("Failed to commit transaction")?;
+
+    Ok(())
+}
+
+fn attachment_info_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<AttachmentInfo> {
+    Ok(AttachmentInfo {
+        id: row.get(0)?,
+        entry_id: row.get(1)?,
+        name: row.get(2)?,
+        size: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+fn ensure_entry(conn: &Connection, entry_id: u64) -> Result<()> {
+    conn.query_row(
+        "SELECT 1 FROM passwords WHERE id = ?1",
+        params![entry_id],
+        |_| Ok(()),
+    )
+    .optional()
+    .context("Failed to query entry")?
+    .ok_or_else(|| anyhow::anyhow!("Entry {} not found", entry_id))
+}
+
+fn touch_entry(conn: &Connection, entry_id: u64) -> Result<()> {
+    conn.execute(
+        "UPDATE passwords SET updated_at = ?1 WHERE id = ?2",
+        params![unix_now(), entry_id],
+    )
+    .context("Failed to update entry")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{connection, operations};
+    use crate::utils::fs::TestDir;
+
+    fn storage_with_entry() -> (Connection, u64) {
+        let conn = connection::open_in_memory().unwrap();
+        let id = operations::add_password(&conn, "GitHub", "octocat", "secret").unwrap();
+        (conn, id)
+    }
+
+    fn attachment_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT count(*) FROM attachments", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn attached_file_is_exported_unchanged() {
+        let dir = TestDir::new("attachments-roundtrip");
+        let (conn, entry_id) = storage_with_entry();
+        // Больше буфера копирования, чтобы содержимое шло несколькими частями
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(dir.join("recovery.pdf"), &data).unwrap();
+
+        let info = attach(&conn, entry_id, &dir.join("recovery.pdf")).unwrap();
+        assert_eq!(info.name, "recovery.pdf");
+        assert_eq!(info.size, data.len() as u64);
+        assert_eq!(list(&conn, entry_id).unwrap().len(), 1);
+
+        export(&conn, info.id, &dir.join("exported.pdf")).unwrap();
+        assert_eq!(std::fs::read(dir.join("exported.pdf")).unwrap(), data);
+
+        delete(&conn, info.id).unwrap();
+        assert!(list(&conn, entry_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn file_over_size_limit_is_rejected() {
+        let dir = TestDir::new("attachments-size");
+        let (conn, entry_id) = storage_with_entry();
+        // Разреженный файл: место на диске не занимает
+        File::create(dir.join("huge.bin"))
+            .unwrap()
+            .set_len(MAX_ATTACHMENT_SIZE + 1)
+            .unwrap();
+
+        let error = attach(&conn, entry_id, &dir.join("huge.bin")).unwrap_err();
+
+        assert!(error.to_string().contains("larger than"), "{error}");
+        assert_eq!(attachment_count(&conn), 0);
+    }
+
+    #[test]
+    fn imported_attachment_over_size_limit_is_rejected() {
+        let (conn, _) = storage_with_entry();
+        let record = operations::EntryRecord {
+            site: "GitLab".to_string(),
+            attachments: vec![operations::AttachmentRecord {
+                name: "huge.bin".to_string(),
+                data: vec![0; MAX_ATTACHMENT_SIZE as usize + 1],
+                created_at: 0,
+            }],
+            ..Default::default()
+        };
+
+        let error = operations::insert_entry_records(&conn, &[record]).unwrap_err();
+
+        assert!(format!("{error:#}").contains("larger than"), "{error:#}");
+        assert_eq!(attachment_count(&conn), 0);
+        assert_eq!(operations::list_entry_records(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn long_name_is_rejected() {
+        let dir = TestDir::new("attachments-name");
+        let (conn, entry_id) = storage_with_entry();
+        let name = "я".repeat(MAX_NAME_LEN + 1);
+
+        // Имя проверяется до открытия файла, поэтому сам файл не нужен
+        let error = attach(&conn, entry_id, &dir.join(name)).unwrap_err();
+
+        assert!(error.to_string().contains("longer than"), "{error}");
+        assert_eq!(attachment_count(&conn), 0);
+    }
+
+    #[test]
+    fn failed_export_removes_partial_file() {
+        /// Отдаёт немного данных, а затем сообщает об ошибке чтения
+        struct Failing(bool);
+
+        impl Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if std::mem::replace(&mut self.0, true) {
+                    return Err(std::io::Error::other("disk error"));
+                }
+                buf[..4].copy_from_slice(b"part");
+                Ok(4)
+            }
+        }
+
+        let dir = TestDir::new("attachments-partial");
+        let destination = dir.join("partial.bin");
+
+        assert!(write_file(&mut Failing(false), &destination).is_err());
+        assert!(!destination.exists());
+    }
+}
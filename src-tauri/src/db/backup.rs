@@ -0,0 +1,663 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use rusqlite::Connection;
+use secrecy::SecretBox as Secret;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::{connection, operations};
+use crate::utils::fs;
+
+/// Расширение копий: не `.db`, чтобы копии не попадали в список хранилищ
+const BACKUP_EXTENSION: &str = "backup";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+/// Сколько байт SHA-256 пути хранилища попадает в имя копии
+const VAULT_TAG_BYTES: usize = 8;
+
+/// Правила создания и хранения резервных копий хранилища
+#[derive(Debug, Clone)]
+pub struct BackupPolicy {
+    pub directory: PathBuf,
+    /// Делать копию после стольких изменений (0 — не делать)
+    pub every_changes: u32,
+    pub keep_count: usize,
+    /// Удалять копии старше стольких дней (0 — не удалять по возрасту)
+    pub max_age_days: u32,
+}
+
+/// Резервная копия хранилища
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub file_name: String,
+    /// Время создания копии, секунды Unix
+    pub created_at: i64,
+    pub size: u64,
+}
+
+/// Делает зашифрованную копию открытого хранилища и удаляет устаревшие копии
+pub fn create(conn: &Connection, vault_path: &Path, policy: &BackupPolicy) -> Result<BackupInfo> {
+    std::fs::create_dir_all(&policy.directory)
+        .with_context(|| format!("Failed to create backup directory: {:?}", policy.directory))?;
+
+    let now = operations::unix_now();
+    let path = next_backup_path(&policy.directory, vault_path, now)?;
+    connection::backup_to(conn, &path)?;
+
+    prune(policy, vault_path, now)?;
+
+    backup_info(&path, now)
+}
+
+/// Возвращает копии хранилища из папки, от новых к старым
+pub fn list(directory: &Path, vault_path: &Path) -> Result<Vec<BackupInfo>> {
+    let backups = list_numbered(directory, vault_path)?
+        .into_iter()
+        .map(|(_, backup)| backup)
+        .collect();
+
+    Ok(backups)
+}
+
+/// Копии хранилища с номерами из имён, от новых к старым
+fn list_numbered(directory: &Path, vault_path: &Path) -> Result<Vec<(u32, BackupInfo)>> {
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+
+    let prefix = backup_prefix(vault_path)?;
+    let mut backups = Vec::new();
+
+    for entry in std::fs::read_dir(directory)
+        .with_context(|| format!("Failed to read backup directory: {:?}", directory))?
+    {
+        let path = entry
+            .context("Failed to read backup directory entry")?
+            .path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some((created_at, sequence)) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| parse_backup_name(name, &prefix))
+        else {
+            continue;
+        };
+        backups.push((sequence, backup_info(&path, created_at)?));
+    }
+
+    // Копии, сделанные в одну секунду, различаются номером после «-»
+    backups.sort_by(|(a_sequence, a), (b_sequence, b)| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then_with(|| b_sequence.cmp(a_sequence))
+    });
+
+    Ok(backups)
+}
+
+/// Сравнение копии с текущим файлом хранилища по числу записей
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreDiff {
+    pub backup_entries: usize,
+    /// `None`, если текущий файл отсутствует или не открывается этим паролем
+    pub current_entries: Option<usize>,
+    /// Насколько изменится число записей после восстановления
+    pub difference: Option<i64>,
+}
+
+/// Итог восстановления
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    pub diff: RestoreDiff,
+    /// Копия заменённого файла хранилища
+    pub replaced: Option<BackupInfo>,
+}
+
+/// Результат восстановления: сравнение без изменений либо итог замены файла
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RestoreOutcome {
+    Preview(RestoreDiff),
+    Restored(RestoreReport),
+}
+
+/// Проверяет копию перед восстановлением: принадлежность хранилищу, пароль
+/// и `integrity_check`, — и сравнивает её с текущим файлом
+pub fn inspect(
+    directory: &Path,
+    backup_path: &Path,
+    vault_path: &Path,
+    master_password: &Secret<String>,
+) -> Result<RestoreDiff> {
+    let belongs_to_vault = list(directory, vault_path)?
+        .iter()
+        .any(|backup| Path::new(&backup.path) == backup_path);
+    if !belongs_to_vault {
+        anyhow::bail!("Backup {:?} does not belong to this vault", backup_path);
+    }
+
+    let backup_entries = connection::verify_storage(backup_path, master_password)
+        .context("Backup cannot be opened with this password or is corrupted")?;
+    let current_entries = vault_path
+        .exists()
+        .then(|| connection::verify_storage(vault_path, master_password).ok())
+        .flatten();
+
+    Ok(RestoreDiff {
+        backup_entries,
+        current_entries,
+        difference: current_entries.map(|current| backup_entries as i64 - current as i64),
+    })
+}
+
+/// Заменяет файл хранилища проверенной копией. Копия сначала пишется во
+/// временный файл рядом с хранилищем и подменяет его одним переименованием;
+/// заменённый файл сохраняется как ещё одна резервная копия.
+/// Хранилище должно быть закрыто.
+pub fn restore(
+    directory: &Path,
+    backup_path: &Path,
+    vault_path: &Path,
+    master_password: &Secret<String>,
+) -> Result<RestoreReport> {
+    let diff = inspect(directory, backup_path, vault_path, master_password)?;
+
+    let file_name = vault_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Invalid vault file name")?;
+    let staging = vault_path.with_file_name(format!(".{file_name}.restore"));
+    stage(backup_path, &staging, master_password)?;
+
+    let replaced = if vault_path.exists() {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create backup directory: {:?}", directory))?;
+        let now = operations::unix_now();
+        let path = next_backup_path(directory, vault_path, now)?;
+        if let Err(e) = copy_private(vault_path, &path) {
+            let _ = std::fs::remove_file(&staging);
+            return Err(e.context("Failed to keep a copy of the replaced vault"));
+        }
+        Some(backup_info(&path, now)?)
+    } else {
+        None
+    };
+
+    if let Err(e) = std::fs::rename(&staging, vault_path) {
+        let _ = std::fs::remove_file(&staging);
+        return Err(e).with_context(|| format!("Failed to replace vault file: {:?}", vault_path));
+    }
+
+    Ok(RestoreReport { diff, replaced })
+}
+
+/// Копирует копию во временный файл и проверяет уже скопированный файл,
+/// а не только исходную копию. При ошибке временный файл удаляется.
+fn stage(backup_path: &Path, staging: &Path, master_password: &Secret<String>) -> Result<()> {
+    let staged = copy_private(backup_path, staging).and_then(|()| {
+        connection::verify_storage(staging, master_password)
+            .map(|_| ())
+            .context("Restored copy failed verification")
+    });
+    if staged.is_err() {
+        let _ = std::fs::remove_file(staging);
+    }
+
+    staged
+}
+
+/// Копирует файл с правами только для владельца и сбрасывает его на диск
+fn copy_private(source: &Path, destination: &Path) -> Result<()> {
+    let data =
+        std::fs::read(source).with_context(|| format!("Failed to read file: {:?}", source))?;
+
+    let mut file = fs::create_private_file(destination)?;
+    file.write_all(&data)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Failed to write file: {:?}", destination))
+}
+
+/// Переименовывает копии вслед за переименованным хранилищем
+pub fn rename_all(directory: &Path, from_vault: &Path, to_vault: &Path) -> Result<()> {
+    let from_prefix = backup_prefix(from_vault)?;
+    let to_prefix = backup_prefix(to_vault)?;
+
+    for backup in list(directory, from_vault)? {
+        let Some(suffix) = backup.file_name.strip_prefix(&from_prefix) else {
+            continue;
+        };
+        let target = directory.join(format!("{to_prefix}{suffix}"));
+        if target.exists() {
+            continue;
+        }
+        std::fs::rename(&backup.path, &target)
+            .with_context(|| format!("Failed to rename backup: {:?}", backup.path))?;
+    }
+
+    Ok(())
+}
+
+/// Оставляет не больше `keep_count` копий и удаляет копии старше `max_age_days`.
+/// Самая свежая копия не удаляется никогда.
+fn prune(policy: &BackupPolicy, vault_path: &Path, now: i64) -> Result<()> {
+    let max_age = i64::from(policy.max_age_days) * SECONDS_PER_DAY;
+
+    for (position, backup) in list(&policy.directory, vault_path)?
+        .into_iter()
+        .enumerate()
+        .skip(1)
+    {
+        let too_many = position >= policy.keep_count.max(1);
+        let too_old = max_age > 0 && now - backup.created_at > max_age;
+        if too_many || too_old {
+            std::fs::remove_file(&backup.path)
+                .with_context(|| format!("Failed to remove old backup: {:?}", backup.path))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Имя копии: `<хранилище>.<метка пути>.<время UTC>[-N].backup`
+fn next_backup_path(directory: &Path, vault_path: &Path, now: i64) -> Result<PathBuf> {
+    let prefix = backup_prefix(vault_path)?;
+    let timestamp = chrono::DateTime::from_timestamp(now, 0)
+        .context("Invalid backup time")?
+        .format(TIMESTAMP_FORMAT)
+        .to_string();
+
+    // Номер больше, чем у любой копии этой секунды: после удаления старых копий
+    // освободившееся имя без номера иначе считалось бы самой старой копией
+    let latest = list_numbered(directory, vault_path)?
+        .into_iter()
+        .filter(|(_, backup)| backup.created_at == now)
+        .map(|(sequence, _)| sequence)
+        .max();
+    let mut counter = latest.map_or(1, |sequence| sequence + 1);
+    let mut path = match counter {
+        1 => directory.join(format!("{prefix}.{timestamp}.{BACKUP_EXTENSION}")),
+        _ => directory.join(format!("{prefix}.{timestamp}-{counter}.{BACKUP_EXTENSION}")),
+    };
+    while path.exists() {
+        counter += 1;
+        path = directory.join(format!("{prefix}.{timestamp}-{counter}.{BACKUP_EXTENSION}"));
+    }
+
+    Ok(path)
+}
+
+/// Время создания и номер копии из её имени или `None`, если файл — не копия этого хранилища
+fn parse_backup_name(file_name: &str, prefix: &str) -> Option<(i64, u32)> {
+    let rest = file_name
+        .strip_prefix(prefix)?
+        .strip_prefix('.')?
+        .strip_suffix(BACKUP_EXTENSION)?
+        .strip_suffix('.')?;
+    let (timestamp, sequence) = match rest.split_once('-') {
+        Some((timestamp, sequence)) => (timestamp, sequence.parse().ok()?),
+        None => (rest, 1),
+    };
+
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()
+        .map(|time| (time.and_utc().timestamp(), sequence))
+}
+
+/// Начало имён копий хранилища: имя файла и метка его полного пути.
+/// Хранилища с одинаковым именем в разных папках получают разные наборы копий.
+fn backup_prefix(vault_path: &Path) -> Result<String> {
+    let stem = vault_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .context("Invalid vault file name")?;
+
+    Ok(format!("{stem}.{}", vault_tag(vault_path)?))
+}
+
+/// Первые байты SHA-256 канонического пути хранилища в шестнадцатеричном виде.
+/// Файла может уже не быть (восстановление, переименование), поэтому
+/// при необходимости канонизируется только папка.
+fn vault_tag(vault_path: &Path) -> Result<String> {
+    let canonical = match vault_path.canonicalize() {
+        Ok(path) => path,
+        Err(_) => {
+            let parent = vault_path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let file_name = vault_path.file_name().context("Invalid vault file name")?;
+            parent
+                .canonicalize()
+                .with_context(|| format!("Vault folder not found: {:?}", parent))?
+                .join(file_name)
+        }
+    };
+
+    Ok(
+        Sha256::digest(canonical.to_string_lossy().as_bytes())[..VAULT_TAG_BYTES]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+    )
+}
+
+fn backup_info(path: &Path, created_at: i64) -> Result<BackupInfo> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read backup metadata: {:?}", path))?
+        .len();
+
+    Ok(BackupInfo {
+        path: path.to_string_lossy().into_owned(),
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        created_at,
+        size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fs::TestDir;
+
+    fn password(value: &str) -> Secret<String> {
+        Secret::new(Box::new(value.to_string()))
+    }
+
+    /// 2024-05-20 12:00:00 UTC
+    const NOW: i64 = 1_716_206_400;
+
+    /// Временная папка теста с файлом хранилища `main.db` и папкой копий
+    fn backup_dir(name: &str) -> TestDir {
+        let dir = TestDir::new(&format!("backup-{name}"));
+        std::fs::create_dir(dir.join("backups")).unwrap();
+        std::fs::write(dir.join("main.db"), b"vault").unwrap();
+        dir
+    }
+
+    fn main_vault(dir: &TestDir) -> PathBuf {
+        dir.join("main.db")
+    }
+
+    fn policy(dir: &TestDir, keep_count: usize, max_age_days: u32) -> BackupPolicy {
+        BackupPolicy {
+            directory: dir.join("backups"),
+            every_changes: 0,
+            keep_count,
+            max_age_days,
+        }
+    }
+
+    /// Пустой файл копии с именем, как у `create`
+    fn add_backup(dir: &TestDir, created_at: i64) -> PathBuf {
+        let path = next_backup_path(&dir.join("backups"), &main_vault(dir), created_at).unwrap();
+        std::fs::write(&path, b"").unwrap();
+        path
+    }
+
+    fn backup_times(dir: &TestDir) -> Vec<i64> {
+        list(&dir.join("backups"), &main_vault(dir))
+            .unwrap()
+            .iter()
+            .map(|backup| backup.created_at)
+            .collect()
+    }
+
+    #[test]
+    fn backup_name_is_parsed() {
+        let prefix = "main.0011223344556677";
+
+        assert_eq!(
+            parse_backup_name("main.0011223344556677.20240520T120000Z.backup", prefix),
+            Some((NOW, 1))
+        );
+        assert_eq!(
+            parse_backup_name("main.0011223344556677.20240520T120000Z-3.backup", prefix),
+            Some((NOW, 3))
+        );
+    }
+
+    #[test]
+    fn foreign_files_are_not_backups() {
+        let prefix = "main.0011223344556677";
+
+        for name in [
+            "main.8899aabbccddeeff.20240520T120000Z.backup",
+            "main.0011223344556677.20240520T120000Z.db",
+            "main.0011223344556677.20240520T120000Z-x.backup",
+            "main.0011223344556677.2024-05-20.backup",
+            "main.20240520T120000Z.backup",
+        ] {
+            assert_eq!(parse_backup_name(name, prefix), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn backups_of_one_second_get_sequence_numbers() {
+        let dir = backup_dir("sequence");
+
+        let first = add_backup(&dir, NOW);
+        let second = add_backup(&dir, NOW);
+        let third = add_backup(&dir, NOW);
+
+        assert!(first
+            .to_string_lossy()
+            .ends_with(".20240520T120000Z.backup"));
+        assert!(second
+            .to_string_lossy()
+            .ends_with(".20240520T120000Z-2.backup"));
+        assert!(third
+            .to_string_lossy()
+            .ends_with(".20240520T120000Z-3.backup"));
+
+        // Новые копии идут первыми, в одной секунде — по номеру
+        let listed: Vec<_> = list(&dir.join("backups"), &main_vault(&dir))
+            .unwrap()
+            .into_iter()
+            .map(|backup| PathBuf::from(backup.path))
+            .collect();
+        assert_eq!(listed, vec![third, second, first]);
+    }
+
+    #[test]
+    fn vaults_with_same_name_have_separate_backups() {
+        let dir = backup_dir("separate");
+        let other = dir.join("nested");
+        std::fs::create_dir_all(&other).unwrap();
+        std::fs::write(other.join("main.db"), b"vault").unwrap();
+
+        add_backup(&dir, NOW);
+
+        assert_eq!(backup_times(&dir), vec![NOW]);
+        assert!(list(&dir.join("backups"), &other.join("main.db"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn prune_keeps_newest_copies() {
+        let dir = backup_dir("keep");
+        for hours in 0..5 {
+            add_backup(&dir, NOW + hours * 3600);
+        }
+
+        prune(&policy(&dir, 3, 0), &main_vault(&dir), NOW + 5 * 3600).unwrap();
+
+        assert_eq!(
+            backup_times(&dir),
+            vec![NOW + 4 * 3600, NOW + 3 * 3600, NOW + 2 * 3600]
+        );
+    }
+
+    #[test]
+    fn prune_removes_old_copies_but_not_the_latest() {
+        let dir = backup_dir("age");
+        add_backup(&dir, NOW - 40 * SECONDS_PER_DAY);
+        add_backup(&dir, NOW - 20 * SECONDS_PER_DAY);
+        add_backup(&dir, NOW - 2 * SECONDS_PER_DAY);
+
+        prune(&policy(&dir, 10, 7), &main_vault(&dir), NOW).unwrap();
+        assert_eq!(backup_times(&dir), vec![NOW - 2 * SECONDS_PER_DAY]);
+
+        // Единственная копия остаётся, даже если она старше срока
+        prune(&policy(&dir, 10, 1), &main_vault(&dir), NOW).unwrap();
+        assert_eq!(backup_times(&dir), vec![NOW - 2 * SECONDS_PER_DAY]);
+    }
+
+    #[test]
+    fn create_writes_a_copy_that_opens_with_vault_password() {
+        let dir = backup_dir("create");
+        let vault = dir.join("work.db");
+        let conn = connection::create_new_storage(&vault, password("пароль")).unwrap();
+        operations::add_password(&conn, "GitHub", "octocat", "secret").unwrap();
+
+        let info = create(&conn, &vault, &policy(&dir, 10, 0)).unwrap();
+
+        assert_eq!(
+            list(&dir.join("backups"), &vault).unwrap()[0].path,
+            info.path
+        );
+        assert_eq!(
+            connection::verify_storage(&info.path, &password("пароль")).unwrap(),
+            1
+        );
+        let copy = connection::open_existing_storage(&info.path, password("пароль")).unwrap();
+        assert_eq!(operations::get_password(&copy, 1).unwrap().login, "octocat");
+    }
+
+    /// Хранилище `work.db` с одной записью и его копия
+    fn vault_with_backup(dir: &TestDir) -> (PathBuf, BackupInfo) {
+        let vault = dir.join("work.db");
+        let conn = connection::create_new_storage(&vault, password("пароль")).unwrap();
+        operations::add_password(&conn, "GitHub", "octocat", "secret").unwrap();
+        let info = create(&conn, &vault, &policy(dir, 10, 0)).unwrap();
+        (vault, info)
+    }
+
+    fn staging_files(dir: &TestDir) -> Vec<String> {
+        std::fs::read_dir(dir.join("."))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".restore"))
+            .collect()
+    }
+
+    #[test]
+    fn inspect_rejects_wrong_password() {
+        let dir = backup_dir("inspect-password");
+        let (vault, info) = vault_with_backup(&dir);
+
+        let result = inspect(
+            &dir.join("backups"),
+            Path::new(&info.path),
+            &vault,
+            &password("чужой"),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn inspect_rejects_backup_of_another_vault() {
+        let dir = backup_dir("inspect-foreign");
+        let (_, info) = vault_with_backup(&dir);
+        let other = dir.join("other.db");
+        connection::create_new_storage(&other, password("пароль")).unwrap();
+
+        let result = inspect(
+            &dir.join("backups"),
+            Path::new(&info.path),
+            &other,
+            &password("пароль"),
+        );
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("does not belong"), "{error}");
+    }
+
+    #[test]
+    fn restore_replaces_vault_and_keeps_replaced_file() {
+        let dir = backup_dir("restore");
+        let (vault, info) = vault_with_backup(&dir);
+        let conn = connection::open_existing_storage(&vault, password("пароль")).unwrap();
+        operations::add_password(&conn, "GitLab", "tanuki", "other").unwrap();
+        drop(conn);
+
+        let report = restore(
+            &dir.join("backups"),
+            Path::new(&info.path),
+            &vault,
+            &password("пароль"),
+        )
+        .unwrap();
+
+        assert_eq!(report.diff.backup_entries, 1);
+        assert_eq!(report.diff.current_entries, Some(2));
+        assert_eq!(report.diff.difference, Some(-1));
+        assert_eq!(
+            connection::verify_storage(&vault, &password("пароль")).unwrap(),
+            1
+        );
+
+        // Заменённый файл — среди копий хранилища и открывается с двумя записями
+        let replaced = report.replaced.unwrap();
+        assert!(list(&dir.join("backups"), &vault)
+            .unwrap()
+            .iter()
+            .any(|backup| backup.path == replaced.path));
+        assert_eq!(
+            connection::verify_storage(&replaced.path, &password("пароль")).unwrap(),
+            2
+        );
+        assert!(staging_files(&dir).is_empty());
+    }
+
+    #[test]
+    fn failed_verification_leaves_no_staging_file() {
+        let dir = backup_dir("restore-corrupt");
+        let (vault, info) = vault_with_backup(&dir);
+
+        // Повреждённая копия не проходит проверку ещё до замены файла
+        std::fs::write(&info.path, b"not a vault").unwrap();
+        assert!(restore(
+            &dir.join("backups"),
+            Path::new(&info.path),
+            &vault,
+            &password("пароль"),
+        )
+        .is_err());
+        assert!(staging_files(&dir).is_empty());
+        assert_eq!(
+            connection::verify_storage(&vault, &password("пароль")).unwrap(),
+            1
+        );
+
+        // Сбой проверки уже скопированного файла тоже убирает временный файл
+        let staging = dir.join(".work.db.restore");
+        assert!(stage(Path::new(&info.path), &staging, &password("пароль")).is_err());
+        assert!(staging_files(&dir).is_empty());
+    }
+
+    #[test]
+    fn rename_moves_backups_to_new_vault_name() {
+        let dir = backup_dir("rename");
+        add_backup(&dir, NOW);
+        let renamed = dir.join("work.db");
+        std::fs::rename(main_vault(&dir), &renamed).unwrap();
+
+        rename_all(&dir.join("backups"), &main_vault(&dir), &renamed).unwrap();
+
+        assert!(backup_times(&dir).is_empty());
+        let moved = list(&dir.join("backups"), &renamed).unwrap();
+        assert_eq!(moved.len(), 1);
+        assert!(moved[0].file_name.starts_with("work."));
+        assert_eq!(moved[0].created_at, NOW);
+    }
+}
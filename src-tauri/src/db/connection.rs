@@ -1,8 +1,14 @@
 use anyhow::{Context, Result};
-use rusqlite::{Connection, OpenFlags};
+use rusqlite::{ffi, params, Connection, OpenFlags};
 use secrecy::{ExposeSecret, SecretBox as Secret};
+use std::os::raw::c_int;
 use std::path::Path;
 
+use crate::utils::{fs, memory};
+
+/// Имя, под которым файл копии подключается к открытому хранилищу
+const BACKUP_SCHEMA: &str = "backup";
+
 /// Настройки безопасности для SQLCipher
 const CIPHER_SETTINGS: &[(&str, &str)] = &[
     ("cipher_page_size", "4096"),
@@ -11,6 +17,99 @@ const CIPHER_SETTINGS: &[(&str, &str)] = &[
     ("cipher_kdf_algorithm", "PBKDF2_HMAC_SHA512"),
 ];
 
+/// Миграции схемы: миграция с индексом N переводит хранилище с версии N на N + 1.
+/// Версия хранится в `PRAGMA user_version`; хранилища без миграций имеют версию 0.
+const MIGRATIONS: &[&str] = &[
+    // v1: адрес сайта, заметки, папка и метки времени
+    r#"
+    ALTER TABLE passwords ADD COLUMN url TEXT;
+    ALTER TABLE passwords ADD COLUMN notes TEXT;
+    ALTER TABLE passwords ADD COLUMN folder TEXT;
+    ALTER TABLE passwords ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE passwords ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+    "#,
+    // v2: TOTP-секрет и пользовательские поля
+    r#"
+    ALTER TABLE passwords ADD COLUMN totp TEXT;
+    CREATE TABLE custom_fields (
+        id INTEGER PRIMARY KEY,
+        entry_id INTEGER NOT NULL REFERENCES passwords(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        value TEXT NOT NULL,
+        hidden INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_custom_fields_entry ON custom_fields(entry_id);
+    "#,
+    // v3: история паролей и вложения
+    r#"
+    CREATE TABLE password_history (
+        id INTEGER PRIMARY KEY,
+        entry_id INTEGER NOT NULL REFERENCES passwords(id) ON DELETE CASCADE,
+        password TEXT NOT NULL,
+        changed_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_password_history_entry ON password_history(entry_id);
+    CREATE TABLE attachments (
+        id INTEGER PRIMARY KEY,
+        entry_id INTEGER NOT NULL REFERENCES passwords(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        data BLOB NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_attachments_entry ON attachments(entry_id);
+    "#,
+    // v4: сведения о хранилище (одна строка); время создания старых хранилищ —
+    // по самой ранней записи
+    r#"
+    CREATE TABLE vault_metadata (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        display_name TEXT NOT NULL DEFAULT '',
+        description TEXT,
+        color TEXT,
+        icon TEXT,
+        created_at INTEGER,
+        app_version TEXT
+    );
+    INSERT INTO vault_metadata (id, created_at)
+    SELECT 1, MIN(NULLIF(created_at, 0)) FROM passwords;
+    "#,
+    // v5: тип записи; все прежние записи — логины
+    r#"
+    ALTER TABLE passwords ADD COLUMN entry_type TEXT NOT NULL DEFAULT 'login';
+    "#,
+    // v6: данные платёжных карт и личные данные, по строке на запись
+    r#"
+    CREATE TABLE cards (
+        entry_id INTEGER PRIMARY KEY REFERENCES passwords(id) ON DELETE CASCADE,
+        holder TEXT NOT NULL,
+        number TEXT NOT NULL,
+        expiry_month INTEGER,
+        expiry_year INTEGER,
+        cvv TEXT,
+        pin TEXT
+    );
+    CREATE TABLE identities (
+        entry_id INTEGER PRIMARY KEY REFERENCES passwords(id) ON DELETE CASCADE,
+        full_name TEXT NOT NULL,
+        address TEXT,
+        phone TEXT,
+        passport_number TEXT,
+        id_number TEXT
+    );
+    "#,
+    // v7: ключи SSH
+    r#"
+    CREATE TABLE ssh_keys (
+        entry_id INTEGER PRIMARY KEY REFERENCES passwords(id) ON DELETE CASCADE,
+        private_key TEXT NOT NULL,
+        public_key TEXT NOT NULL,
+        fingerprint TEXT NOT NULL,
+        comment TEXT NOT NULL
+    );
+    "#,
+];
+
 /// Проверяет, существует ли хранилище по указанному пути
 pub fn storage_exists<P: AsRef<Path>>(path: P) -> bool {
     path.as_ref().is_file()
@@ -44,9 +143,11 @@ pub fn create_new_storage<P: AsRef<Path>>(
     // Устанавливаем шифрование
     setup_encryption(&conn, &master_password)
         .context("Failed to initialize encryption for new storage")?;
+    enable_foreign_keys(&conn)?;
 
     // Инициализируем структуру БД
     initialize_storage_schema(&conn).context("Failed to initialize new storage schema")?;
+    migrate_schema(&conn).context("Failed to migrate new storage schema")?;
 
     // Проверяем целостность только что созданной БД
     verify_integrity(&conn).context("Critical error: newly created storage is corrupted")?;
@@ -75,19 +176,131 @@ pub fn open_existing_storage<P: AsRef<Path>>(
 
     // Устанавливаем шифрование
     setup_encryption(&conn, &master_password).context("Invalid password or corrupted storage")?;
+    enable_foreign_keys(&conn)?;
 
     // Проверяем целостность существующей БД
     verify_integrity(&conn)
         .context("Storage corruption detected. Possible causes: wrong password or disk error")?;
 
+    // Доводим схему старых хранилищ до текущей версии
+    migrate_schema(&conn).context("Failed to migrate storage schema")?;
+
     Ok(conn)
 }
 
+/// Проверяет мастер-пароль, открывая отдельное соединение только для чтения
+pub fn check_password<P: AsRef<Path>>(path: P, master_password: &Secret<String>) -> Result<()> {
+    let path = path.as_ref();
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open storage file: {:?}", path))?;
+
+    setup_encryption(&conn, master_password).context("Failed to set encryption key")?;
+
+    // С неверным ключом SQLCipher не сможет прочитать даже схему
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        .context("Invalid master password")?;
+
+    Ok(())
+}
+
+/// Открывает файл хранилища только для чтения, проверяет пароль и целостность
+/// и возвращает число записей в нём
+pub fn verify_storage<P: AsRef<Path>>(path: P, master_password: &Secret<String>) -> Result<usize> {
+    let path = path.as_ref();
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open storage file: {:?}", path))?;
+
+    setup_encryption(&conn, master_password).context("Failed to set encryption key")?;
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        .context("Invalid master password")?;
+    verify_integrity(&conn)?;
+
+    let entries: i64 = conn
+        .query_row("SELECT count(*) FROM passwords", [], |row| row.get(0))
+        .context("Failed to count entries")?;
+
+    usize::try_from(entries).context("Invalid entry count")
+}
+
+/// Копирует открытое хранилище в новый файл через `sqlcipher_export`.
+/// Файл подключается без ключа, поэтому SQLCipher шифрует его ключом самого
+/// хранилища — мастер-пароль для копии не нужен и в памяти не хранится.
+///
+/// Online backup API SQLite (`rusqlite::backup::Backup`) здесь не подходит:
+/// SQLCipher копирует страницы только в базу, открытую с тем же ключом,
+/// а для отдельного соединения с копией пришлось бы держать мастер-пароль
+/// всё время, пока хранилище открыто.
+pub fn backup_to<P: AsRef<Path>>(conn: &Connection, destination: P) -> Result<()> {
+    let destination = destination.as_ref();
+
+    if destination.exists() {
+        anyhow::bail!("Backup file already exists: {:?}", destination);
+    }
+    // Создаём файл заранее, чтобы у копии сразу были права только для владельца
+    fs::create_private_file(destination)?;
+
+    let result = copy_to(conn, destination);
+
+    // Недописанная копия хуже отсутствующей
+    if result.is_err() {
+        let _ = std::fs::remove_file(destination);
+    }
+
+    result
+}
+
+fn copy_to(conn: &Connection, destination: &Path) -> Result<()> {
+    let path = destination
+        .to_str()
+        .with_context(|| format!("Invalid backup path: {:?}", destination))?;
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {BACKUP_SCHEMA}"),
+        params![path],
+    )
+    .with_context(|| format!("Failed to open backup file: {:?}", destination))?;
+
+    let result = export_to_attached(conn);
+    let detached = conn
+        .execute(&format!("DETACH DATABASE {BACKUP_SCHEMA}"), [])
+        .context("Failed to close backup file");
+
+    result.and(detached.map(|_| ()))
+}
+
+fn export_to_attached(conn: &Connection) -> Result<()> {
+    // Настройки шифрования применяются до первого обращения к файлу копии
+    for (pragma, value) in CIPHER_SETTINGS {
+        conn.pragma_update(Some(BACKUP_SCHEMA), pragma, value)
+            .with_context(|| format!("Failed to configure backup {}: {}", pragma, value))?;
+    }
+
+    conn.query_row(
+        &format!("SELECT sqlcipher_export('{BACKUP_SCHEMA}')"),
+        [],
+        |_| Ok(()),
+    )
+    .context("Failed to copy storage to backup")?;
+
+    // sqlcipher_export не переносит версию схемы, без неё копия мигрировала бы повторно
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .context("Failed to read schema version")?;
+    conn.pragma_update(Some(BACKUP_SCHEMA), "user_version", version)
+        .context("Failed to write backup schema version")
+}
+
 /// Общая логика установки шифрования
 fn setup_encryption(conn: &Connection, master_password: &Secret<String>) -> Result<()> {
-    // Устанавливаем ключ шифрования
-    conn.pragma_update(None, "key", master_password.expose_secret().as_str())
-        .context("Failed to set encryption key")?;
+    // Просим SQLCipher блокировать и затирать память с ключами
+    conn.pragma_update(None, "cipher_memory_security", "ON")
+        .context("Failed to enable cipher memory security")?;
+
+    // Ключ передаётся через sqlite3_key, а не `PRAGMA key`: текст запроса с паролем
+    // лежал бы в обычной памяти и не затирался
+    let password = memory::locked_copy(master_password.expose_secret().as_bytes());
+    set_key(conn, &password).context("Failed to set encryption key")?;
 
     // Применяем настройки шифрования
     for (pragma, value) in CIPHER_SETTINGS {
@@ -98,6 +311,26 @@ fn setup_encryption(conn: &Connection, master_password: &Secret<String>) -> Resu
     Ok(())
 }
 
+/// Передаёт SQLCipher пароль из закреплённого буфера
+fn set_key(conn: &Connection, password: &[u8]) -> Result<()> {
+    let len = c_int::try_from(password.len()).context("Master password is too long")?;
+
+    // SAFETY: соединение открыто и используется только этим потоком;
+    // SQLCipher копирует пароль и не хранит указатель после возврата
+    let code = unsafe { ffi::sqlite3_key(conn.handle(), password.as_ptr().cast(), len) };
+    if code != ffi::SQLITE_OK {
+        anyhow::bail!("sqlite3_key failed with code {}", code);
+    }
+
+    Ok(())
+}
+
+/// Включает внешние ключи, чтобы связанные данные удалялись вместе с записью
+fn enable_foreign_keys(conn: &Connection) -> Result<()> {
+    conn.pragma_update(None, "foreign_keys", "ON")
+        .context("Failed to enable foreign keys")
+}
+
 /// Инициализирует структуру новой БД
 fn initialize_storage_schema(conn: &Connection) -> Result<()> {
     conn.execute_batch(
@@ -115,9 +348,282 @@ fn initialize_storage_schema(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
-/// Проверяет целостность расшифрованной БД
+/// Применяет недостающие миграции схемы, каждую в своей транзакции
+fn migrate_schema(conn: &Connection) -> Result<()> {
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .context("Failed to read schema version")?;
+    let version = usize::try_from(version).context("Invalid schema version")?;
+
+    if version > MIGRATIONS.len() {
+        anyhow::bail!(
+            "Storage schema version {} is newer than supported ({})",
+            version,
+            MIGRATIONS.len()
+        );
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn
+            .unchecked_transaction()
+            .context("Failed to start migration")?;
+        tx.execute_batch(migration)
+            .with_context(|| format!("Failed to apply schema migration {}", index + 1))?;
+        tx.pragma_update(None, "user_version", (index + 1) as i64)
+            .context("Failed to update schema version")?;
+        tx.commit().context("Failed to commit migration")?;
+    }
+
+    Ok(())
+}
+
+/// Проверяет целостность расшифрованной БД: `PRAGMA integrity_check` должен вернуть «ok»
 pub fn verify_integrity(conn: &Connection) -> Result<()> {
-    conn.query_row("PRAGMA integrity_check", [], |_| Ok(()))
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
         .context("Database corruption detected")?;
+    let problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .context("Database corruption detected")?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Database corruption detected")?;
+
+    if problems.len() != 1 || problems[0] != "ok" {
+        anyhow::bail!("Database corruption detected: {}", problems.join("; "));
+    }
+
     Ok(())
 }
+
+/// Незашифрованное хранилище в памяти с текущей схемой для тестов
+#[cfg(test)]
+pub(crate) fn open_in_memory() -> Result<Connection> {
+    let conn = Connection::open_in_memory().context("Failed to open in-memory storage")?;
+    enable_foreign_keys(&conn)?;
+    initialize_storage_schema(&conn)?;
+    migrate_schema(&conn)?;
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::operations::{self, CardDetails, IdentityDetails, SummaryDetails};
+    use crate::utils::card::CardBrand;
+    use crate::utils::fs::TestDir;
+
+    /// Незашифрованное хранилище в памяти со схемой версии `version`
+    fn storage_at(version: usize) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        enable_foreign_keys(&conn).unwrap();
+        initialize_storage_schema(&conn).unwrap();
+        for migration in &MIGRATIONS[..version] {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", version as i64)
+            .unwrap();
+        conn
+    }
+
+    fn schema_version(conn: &Connection) -> i64 {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn v5_turns_existing_entries_into_logins() {
+        let conn = storage_at(4);
+        conn.execute(
+            "INSERT INTO passwords (site, login, password) VALUES ('GitHub', 'octocat', 'secret')",
+            [],
+        )
+        .unwrap();
+
+        migrate_schema(&conn).unwrap();
+
+        assert_eq!(schema_version(&conn), MIGRATIONS.len() as i64);
+        let entry_type: String = conn
+            .query_row("SELECT entry_type FROM passwords", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(entry_type, "login");
+
+        let services = operations::list_services(&conn).unwrap();
+        assert_eq!(services.len(), 1);
+        let SummaryDetails::Login { site, login } = &services[0].details else {
+            panic!("migrated entry is not a login");
+        };
+        assert_eq!((site.as_str(), login.as_str()), ("GitHub", "octocat"));
+    }
+
+    #[test]
+    fn v5_allows_secure_notes() {
+        let conn = storage_at(4);
+        migrate_schema(&conn).unwrap();
+
+        let id = operations::add_secure_note(&conn, "Wi-Fi", "**пароль** на роутере").unwrap();
+
+        let note = operations::get_secure_note(&conn, id).unwrap();
+        assert_eq!(note.title, "Wi-Fi");
+        assert_eq!(note.body, "**пароль** на роутере");
+        assert!(operations::get_password(&conn, id).is_err());
+    }
+
+    #[test]
+    fn v6_stores_cards_and_identities() {
+        let conn = storage_at(5);
+        migrate_schema(&conn).unwrap();
+
+        let card = CardDetails {
+            holder: "IVAN PETROV".to_string(),
+            number: "4111111111111111".to_string(),
+            expiry_month: Some(3),
+            expiry_year: Some(2029),
+            cvv: Some("123".to_string()),
+            pin: None,
+        };
+        let card_id = operations::add_card(&conn, "Зарплатная", &card).unwrap();
+        let identity = IdentityDetails {
+            full_name: "Иван Петров".to_string(),
+            passport_number: Some("4510 123456".to_string()),
+            ..Default::default()
+        };
+        let identity_id = operations::add_identity(&conn, "Паспорт", &identity).unwrap();
+
+        assert_eq!(operations::get_card(&conn, card_id).unwrap().details, card);
+        assert_eq!(
+            operations::get_identity(&conn, identity_id)
+                .unwrap()
+                .details,
+            identity
+        );
+
+        let services = operations::list_services(&conn).unwrap();
+        let SummaryDetails::Card {
+            brand,
+            number,
+            expiry,
+            ..
+        } = &services[0].details
+        else {
+            panic!("first entry is not a card");
+        };
+        assert_eq!(*brand, Some(CardBrand::Visa));
+        assert_eq!(number, "•••• 1111");
+        assert_eq!(expiry.as_deref(), Some("03/29"));
+        let SummaryDetails::Identity { document, .. } = &services[1].details else {
+            panic!("second entry is not an identity");
+        };
+        assert_eq!(document.as_deref(), Some("•••• 3456"));
+    }
+
+    #[test]
+    fn v6_details_are_deleted_with_entry() {
+        let conn = storage_at(5);
+        migrate_schema(&conn).unwrap();
+        let id = operations::add_card(&conn, "Visa", &CardDetails::default()).unwrap();
+
+        operations::delete_password(&conn, id).unwrap();
+
+        let cards: i64 = conn
+            .query_row("SELECT count(*) FROM cards", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cards, 0);
+    }
+
+    fn password(value: &str) -> Secret<String> {
+        Secret::new(Box::new(value.to_string()))
+    }
+
+    #[test]
+    fn keyed_storage_opens_only_with_its_password() {
+        let dir = TestDir::new("connection-key");
+        let path = dir.join("main.db");
+
+        let conn = create_new_storage(&path, password("correct horse")).unwrap();
+        operations::add_password(&conn, "GitHub", "octocat", "secret").unwrap();
+        drop(conn);
+
+        // Файл зашифрован: обычного заголовка SQLite в нём нет
+        let header = std::fs::read(&path).unwrap();
+        assert!(!header.starts_with(b"SQLite format 3"));
+
+        let conn = open_existing_storage(&path, password("correct horse")).unwrap();
+        assert_eq!(operations::list_services(&conn).unwrap().len(), 1);
+        drop(conn);
+
+        assert!(open_existing_storage(&path, password("wrong horse")).is_err());
+        assert!(check_password(&path, &password("wrong horse")).is_err());
+        assert_eq!(
+            verify_storage(&path, &password("correct horse")).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn storage_keyed_with_pragma_opens_through_sqlite3_key() {
+        let dir = TestDir::new("connection-pragma");
+        let path = dir.join("old.db");
+
+        // Так ключ задавался раньше: хранилища, созданные до перехода на sqlite3_key
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "key", "пароль").unwrap();
+        for (pragma, value) in CIPHER_SETTINGS {
+            conn.pragma_update(None, pragma, value).unwrap();
+        }
+        initialize_storage_schema(&conn).unwrap();
+        drop(conn);
+
+        let conn = open_existing_storage(&path, password("пароль")).unwrap();
+        assert_eq!(schema_version(&conn), MIGRATIONS.len() as i64);
+        assert!(operations::list_services(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn backup_opens_with_vault_password_at_current_version() {
+        let dir = TestDir::new("connection-backup");
+        let conn = create_new_storage(dir.join("main.db"), password("пароль")).unwrap();
+        operations::add_password(&conn, "GitHub", "octocat", "secret").unwrap();
+        operations::add_secure_note(&conn, "Wi-Fi", "guest").unwrap();
+
+        backup_to(&conn, dir.join("main.backup")).unwrap();
+        assert!(backup_to(&conn, dir.join("main.backup")).is_err());
+
+        // Открываем без миграций, чтобы увидеть версию схемы, записанную в копию
+        let backup =
+            Connection::open_with_flags(dir.join("main.backup"), OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .unwrap();
+        setup_encryption(&backup, &password("пароль")).unwrap();
+        verify_integrity(&backup).unwrap();
+        assert_eq!(schema_version(&backup), MIGRATIONS.len() as i64);
+
+        assert_eq!(operations::list_services(&backup).unwrap().len(), 2);
+        assert_eq!(
+            operations::get_password(&backup, 1).unwrap().password,
+            "secret"
+        );
+        assert_eq!(
+            operations::get_secure_note(&backup, 2).unwrap().body,
+            "guest"
+        );
+
+        assert!(verify_storage(dir.join("main.backup"), &password("чужой")).is_err());
+    }
+
+    #[test]
+    fn migrations_are_applied_once() {
+        let conn = storage_at(MIGRATIONS.len());
+
+        migrate_schema(&conn).unwrap();
+
+        assert_eq!(schema_version(&conn), MIGRATIONS.len() as i64);
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let conn = storage_at(MIGRATIONS.len());
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() as i64 + 1)
+            .unwrap();
+
+        assert!(migrate_schema(&conn).is_err());
+    }
+}
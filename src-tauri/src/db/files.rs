@@ -0,0 +1,219 @@
+use anyhow::{Context, Result};
+use rand::RngCore;
+use secrecy::SecretBox as Secret;
+use serde::Deserialize;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::connection;
+use crate::utils::fs;
+
+/// Размер блока при затирании файла
+const WIPE_CHUNK: usize = 64 * 1024;
+
+/// Способ удаления файла хранилища
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteMode {
+    /// Переместить в корзину ОС (можно восстановить)
+    Trash,
+    /// Перезаписать содержимое случайными данными и удалить.
+    /// На SSD и файловых системах с копированием при записи не гарантирует,
+    /// что старые блоки физически стёрты.
+    SecureOverwrite,
+}
+
+/// Переименовывает файл хранилища; файл с новым именем не должен существовать.
+/// Новое имя создаётся жёсткой ссылкой: в отличие от `rename`, она не заменит
+/// файл, появившийся под этим именем после проверки.
+pub fn rename(from: &Path, to: &Path) -> Result<()> {
+    ensure_vault_file(from)?;
+    ensure_free(to)?;
+
+    match std::fs::hard_link(from, to) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            anyhow::bail!("Vault already exists: {:?}", to);
+        }
+        // Файловая система без жёстких ссылок (FAT и некоторые сетевые диски)
+        Err(_) => {
+            return std::fs::rename(from, to)
+                .with_context(|| format!("Failed to rename vault to {:?}", to));
+        }
+    }
+
+    if let Err(e) = std::fs::remove_file(from) {
+        let _ = std::fs::remove_file(to);
+        return Err(e).with_context(|| format!("Failed to rename vault to {:?}", to));
+    }
+
+    Ok(())
+}
+
+/// Копирует файл хранилища под новым именем с правами только для владельца.
+/// Копия зашифрована тем же мастер-паролем.
+pub fn duplicate(from: &Path, to: &Path) -> Result<()> {
+    ensure_vault_file(from)?;
+    ensure_free(to)?;
+
+    let data = std::fs::read(from).with_context(|| format!("Failed to read vault: {:?}", from))?;
+    // Файл, появившийся под этим именем после проверки, не перезаписывается
+    let mut file = fs::create_new_private_file(to)?;
+    let result = file
+        .write_all(&data)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Failed to write vault copy: {:?}", to));
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(to);
+    }
+
+    result
+}
+
+/// Проверяет мастер-пароль хранилища перед удалением
+pub fn check_password(path: &Path, master_password: &Secret<String>) -> Result<()> {
+    ensure_vault_file(path)?;
+    connection::check_password(path, master_password)
+}
+
+/// Удаляет файл хранилища. Мастер-пароль проверяется заранее через
+/// [`check_password`] — до того, как будут закрыты сессии с этим файлом.
+pub fn delete(path: &Path, mode: DeleteMode) -> Result<()> {
+    ensure_vault_file(path)?;
+
+    match mode {
+        DeleteMode::Trash => trash::delete(path)
+            .with_context(|| format!("Failed to move vault to trash: {:?}", path)),
+        DeleteMode::SecureOverwrite => {
+            wipe(path)?;
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to delete vault: {:?}", path))
+        }
+    }
+}
+
+/// Перезаписывает содержимое файла случайными байтами и сбрасывает на диск
+fn wipe(path: &Path) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open vault for overwrite: {:?}", path))?;
+    let mut remaining = file
+        .metadata()
+        .with_context(|| format!("Failed to read vault metadata: {:?}", path))?
+        .len();
+
+    file.seek(SeekFrom::Start(0))
+        .context("Failed to overwrite vault")?;
+
+    let mut chunk = vec![0u8; WIPE_CHUNK];
+    while remaining > 0 {
+        let len = remaining.min(WIPE_CHUNK as u64) as usize;
+        rand::rng().fill_bytes(&mut chunk[..len]);
+        file.write_all(&chunk[..len])
+            .context("Failed to overwrite vault")?;
+        remaining -= len as u64;
+    }
+
+    file.sync_all().context("Failed to flush overwritten vault")
+}
+
+fn ensure_vault_file(path: &Path) -> Result<()> {
+    if !path.is_file() {
+        anyhow::bail!("Vault not found: {:?}", path);
+    }
+    Ok(())
+}
+
+/// Проверяет, что имя ещё не занято
+pub fn ensure_free(path: &Path) -> Result<()> {
+    if path.exists() {
+        anyhow::bail!("Vault already exists: {:?}", path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fs::TestDir;
+
+    fn password(value: &str) -> Secret<String> {
+        Secret::new(Box::new(value.to_string()))
+    }
+
+    #[test]
+    fn rename_moves_file_to_free_name() {
+        let dir = TestDir::new("files-rename");
+        std::fs::write(dir.join("main.db"), b"vault").unwrap();
+
+        rename(&dir.join("main.db"), &dir.join("work.db")).unwrap();
+
+        assert!(!dir.join("main.db").exists());
+        assert_eq!(std::fs::read(dir.join("work.db")).unwrap(), b"vault");
+    }
+
+    #[test]
+    fn rename_and_duplicate_keep_existing_files() {
+        let dir = TestDir::new("files-taken");
+        std::fs::write(dir.join("main.db"), b"main").unwrap();
+        std::fs::write(dir.join("work.db"), b"work").unwrap();
+
+        assert!(rename(&dir.join("main.db"), &dir.join("work.db")).is_err());
+        assert!(duplicate(&dir.join("main.db"), &dir.join("work.db")).is_err());
+        assert!(rename(&dir.join("missing.db"), &dir.join("other.db")).is_err());
+
+        assert_eq!(std::fs::read(dir.join("main.db")).unwrap(), b"main");
+        assert_eq!(std::fs::read(dir.join("work.db")).unwrap(), b"work");
+        assert!(!dir.join("other.db").exists());
+    }
+
+    #[test]
+    fn duplicate_copies_contents() {
+        let dir = TestDir::new("files-duplicate");
+        std::fs::write(dir.join("main.db"), b"vault").unwrap();
+
+        duplicate(&dir.join("main.db"), &dir.join("copy.db")).unwrap();
+
+        assert_eq!(std::fs::read(dir.join("main.db")).unwrap(), b"vault");
+        assert_eq!(std::fs::read(dir.join("copy.db")).unwrap(), b"vault");
+    }
+
+    #[test]
+    fn wipe_overwrites_whole_file() {
+        let dir = TestDir::new("files-wipe");
+        let data = vec![0u8; WIPE_CHUNK * 2 + 100];
+        std::fs::write(dir.join("main.db"), &data).unwrap();
+
+        wipe(&dir.join("main.db")).unwrap();
+
+        let wiped = std::fs::read(dir.join("main.db")).unwrap();
+        assert_eq!(wiped.len(), data.len());
+        // Каждый блок перезаписан: случайный блок из одних нулей невероятен
+        assert!(wiped
+            .chunks(WIPE_CHUNK)
+            .all(|chunk| chunk.iter().any(|&b| b != 0)));
+    }
+
+    #[test]
+    fn secure_delete_removes_file() {
+        let dir = TestDir::new("files-delete");
+        std::fs::write(dir.join("main.db"), b"vault").unwrap();
+
+        delete(&dir.join("main.db"), DeleteMode::SecureOverwrite).unwrap();
+
+        assert!(!dir.join("main.db").exists());
+        assert!(delete(&dir.join("main.db"), DeleteMode::SecureOverwrite).is_err());
+    }
+
+    #[test]
+    fn deletion_requires_vault_password() {
+        let dir = TestDir::new("files-password");
+        connection::create_new_storage(dir.join("main.db"), password("пароль")).unwrap();
+
+        assert!(check_password(&dir.join("main.db"), &password("пароль")).is_ok());
+        assert!(check_password(&dir.join("main.db"), &password("чужой")).is_err());
+        assert!(check_password(&dir.join("missing.db"), &password("пароль")).is_err());
+    }
+}
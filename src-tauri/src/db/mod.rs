@@ -1,5 +1,11 @@
+pub mod attachments;
+pub mod backup;
 mod connection;
+pub mod files;
 pub mod operations;
+pub mod registry;
+#[cfg(unix)]
+pub mod ssh_agent;
 
 use anyhow::{Context, Result};
 use rusqlite::Connection;
@@ -17,6 +23,17 @@ struct VaultInner {
     path: PathBuf,
     connection: Option<Connection>,
     is_locked: bool,
+    backups: Option<BackupState>,
+    /// Встроенный ssh-agent; останавливается вместе с блокировкой хранилища
+    #[cfg(unix)]
+    agent: Option<ssh_agent::SshAgent>,
+}
+
+/// Автоматические копии открытого хранилища. Копии шифруются ключом уже
+/// открытого соединения, поэтому мастер-пароль здесь не хранится.
+struct BackupState {
+    policy: backup::BackupPolicy,
+    changes_since_backup: u32,
 }
 
 impl Vault {
@@ -27,6 +44,9 @@ impl Vault {
                 path: path.into(),
                 connection: None,
                 is_locked: true,
+                backups: None,
+                #[cfg(unix)]
+                agent: None,
             })),
         }
     }
@@ -73,6 +93,14 @@ impl Vault {
 
         inner.connection = None;
         inner.is_locked = true;
+        inner.backups = None;
+        #[cfg(unix)]
+        let agent = inner.agent.take();
+        drop(inner);
+
+        // Агент ждёт свои потоки, а им может понадобиться блокировка хранилища
+        #[cfg(unix)]
+        drop(agent);
 
         Ok(())
     }
@@ -82,8 +110,8 @@ impl Vault {
         self.inner().is_locked.not()
     }
 
-    /// Возвращает список всех сервисов
-    pub fn list_services(&self) -> Result<Vec<operations::ServiceSummary>> {
+    /// Возвращает краткие описания всех записей
+    pub fn list_services(&self) -> Result<Vec<operations::EntrySummary>> {
         let inner = self.inner();
 
         let conn = inner
@@ -94,6 +122,18 @@ impl Vault {
         operations::list_services(conn)
     }
 
+    /// Ищет записи по названию, логину и адресу
+    pub fn search(&self, query: &str) -> Result<Vec<operations::EntrySummary>> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::search_services(conn, query)
+    }
+
     /// Возвращает пароль по ID
     pub fn get_password(&self, id: u64) -> Result<operations::PasswordEntry> {
         let inner = self.inner();
@@ -108,18 +148,330 @@ impl Vault {
 
     /// Добавляет новую запись
     pub fn add_password(&self, site: &str, login: &str, password: &str) -> Result<u64> {
-        let inner = self.inner();
+        let mut inner = self.inner();
 
         let conn = inner
             .connection
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
 
-        operations::add_password(conn, site, login, password)
+        let id = operations::add_password(conn, site, login, password)?;
+        inner.note_changes(1);
+
+        Ok(id)
     }
 
     /// Удаляет запись по ID
     pub fn delete_password(&self, id: u64) -> Result<()> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::delete_password(conn, id)?;
+        inner.note_changes(1);
+
+        Ok(())
+    }
+
+    /// Добавляет заметку
+    pub fn add_secure_note(&self, title: &str, body: &str) -> Result<u64> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        let id = operations::add_secure_note(conn, title, body)?;
+        inner.note_changes(1);
+
+        Ok(id)
+    }
+
+    /// Возвращает заметку по ID
+    pub fn get_secure_note(&self, id: u64) -> Result<operations::SecureNote> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::get_secure_note(conn, id)
+    }
+
+    /// Изменяет заголовок и текст заметки
+    pub fn update_secure_note(&self, id: u64, title: &str, body: &str) -> Result<()> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::update_secure_note(conn, id, title, body)?;
+        inner.note_changes(1);
+
+        Ok(())
+    }
+
+    /// Добавляет платёжную карту после проверки номера и сроков
+    pub fn add_card(&self, title: &str, details: &operations::CardDetails) -> Result<u64> {
+        let details = details.validated()?;
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        let id = operations::add_card(conn, title, &details)?;
+        inner.note_changes(1);
+
+        Ok(id)
+    }
+
+    /// Возвращает платёжную карту по ID
+    pub fn get_card(&self, id: u64) -> Result<operations::Card> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::get_card(conn, id)
+    }
+
+    /// Изменяет платёжную карту после проверки номера и сроков
+    pub fn update_card(
+        &self,
+        id: u64,
+        title: &str,
+        details: &operations::CardDetails,
+    ) -> Result<()> {
+        let details = details.validated()?;
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::update_card(conn, id, title, &details)?;
+        inner.note_changes(1);
+
+        Ok(())
+    }
+
+    /// Добавляет личные данные
+    pub fn add_identity(&self, title: &str, details: &operations::IdentityDetails) -> Result<u64> {
+        let details = details.normalized();
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        let id = operations::add_identity(conn, title, &details)?;
+        inner.note_changes(1);
+
+        Ok(id)
+    }
+
+    /// Возвращает личные данные по ID
+    pub fn get_identity(&self, id: u64) -> Result<operations::Identity> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::get_identity(conn, id)
+    }
+
+    /// Изменяет личные данные
+    pub fn update_identity(
+        &self,
+        id: u64,
+        title: &str,
+        details: &operations::IdentityDetails,
+    ) -> Result<()> {
+        let details = details.normalized();
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::update_identity(conn, id, title, &details)?;
+        inner.note_changes(1);
+
+        Ok(())
+    }
+
+    /// Добавляет ключ SSH
+    pub fn add_ssh_key(&self, title: &str, details: &operations::SshKeyDetails) -> Result<u64> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        let id = operations::add_ssh_key(conn, title, details)?;
+        inner.note_changes(1);
+
+        Ok(id)
+    }
+
+    /// Возвращает ключ SSH по ID
+    pub fn get_ssh_key(&self, id: u64) -> Result<operations::SshKey> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::get_ssh_key(conn, id)
+    }
+
+    /// Запускает ssh-agent с ключами этого хранилища. `confirm` спрашивается
+    /// перед каждой подписью; без него ключи используются без подтверждения.
+    #[cfg(unix)]
+    pub fn start_ssh_agent(
+        &self,
+        socket_path: &std::path::Path,
+        confirm: Option<ssh_agent::Confirm>,
+    ) -> Result<PathBuf> {
+        let mut inner = self.inner();
+
+        if inner.is_locked {
+            anyhow::bail!("Vault is locked");
+        }
+        if let Some(agent) = &inner.agent {
+            anyhow::bail!("SSH agent is already running: {:?}", agent.socket_path());
+        }
+
+        let agent = ssh_agent::SshAgent::start(socket_path, Arc::downgrade(&self.inner), confirm)?;
+        let path = agent.socket_path().to_path_buf();
+        inner.agent = Some(agent);
+
+        Ok(path)
+    }
+
+    /// Останавливает ssh-agent, если он запущен
+    #[cfg(unix)]
+    pub fn stop_ssh_agent(&self) {
+        let agent = self.inner().agent.take();
+        drop(agent);
+    }
+
+    /// Проверяет мастер-пароль открытого хранилища (для подтверждения опасных действий)
+    pub fn verify_password(&self, master_password: &Secret<String>) -> Result<()> {
+        let inner = self.inner();
+
+        if inner.is_locked {
+            anyhow::bail!("Vault is locked");
+        }
+
+        connection::check_password(&inner.path, master_password)
+    }
+
+    /// Возвращает все записи со всеми полями (для экспорта)
+    pub fn export_records(&self) -> Result<Vec<operations::EntryRecord>> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::list_entry_records(conn)
+    }
+
+    /// Возвращает записи с указанными ID со всеми полями
+    pub fn entry_records(&self, ids: &[u64]) -> Result<Vec<operations::EntryRecord>> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::get_entry_records(conn, ids)
+    }
+
+    /// Удаляет записи с указанными ID в одной транзакции
+    pub fn delete_records(&self, ids: &[u64]) -> Result<()> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::delete_entry_records(conn, ids)?;
+        inner.note_changes(ids.len());
+
+        Ok(())
+    }
+
+    /// Добавляет набор записей в одной транзакции
+    pub fn import_records(&self, records: &[operations::EntryRecord]) -> Result<Vec<u64>> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        let ids = operations::insert_entry_records(conn, records)?;
+        inner.note_changes(records.len());
+
+        Ok(ids)
+    }
+
+    /// Возвращает все записи вместе с их ID, без вложений
+    pub fn identified_records(&self) -> Result<Vec<(u64, operations::EntryRecord)>> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::list_identified_records(conn)
+    }
+
+    /// Добавляет новые записи и заменяет существующие в одной транзакции
+    pub fn apply_records(
+        &self,
+        new_records: &[operations::EntryRecord],
+        replacements: &[(u64, operations::EntryRecord)],
+    ) -> Result<Vec<u64>> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        let ids = operations::apply_entry_records(conn, new_records, replacements)?;
+        inner.note_changes(new_records.len() + replacements.len());
+
+        Ok(ids)
+    }
+
+    /// Возвращает вложения записи без содержимого
+    pub fn attachments(&self, entry_id: u64) -> Result<Vec<attachments::AttachmentInfo>> {
         let inner = self.inner();
 
         let conn = inner
@@ -127,7 +479,147 @@ impl Vault {
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
 
-        operations::delete_password(conn, id)
+        attachments::list(conn, entry_id)
+    }
+
+    /// Возвращает сведения о вложении по ID
+    pub fn attachment(&self, id: u64) -> Result<attachments::AttachmentInfo> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        attachments::get(conn, id)
+    }
+
+    /// Добавляет файл к записи
+    pub fn attach_file(
+        &self,
+        entry_id: u64,
+        source: &std::path::Path,
+    ) -> Result<attachments::AttachmentInfo> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        let attachment = attachments::attach(conn, entry_id, source)?;
+        inner.note_changes(1);
+
+        Ok(attachment)
+    }
+
+    /// Сохраняет вложение в файл
+    pub fn export_attachment(&self, id: u64, destination: &std::path::Path) -> Result<()> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        attachments::export(conn, id, destination)
+    }
+
+    /// Удаляет вложение
+    pub fn delete_attachment(&self, id: u64) -> Result<()> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        attachments::delete(conn, id)?;
+        inner.note_changes(1);
+
+        Ok(())
+    }
+
+    /// Возвращает сведения о хранилище
+    pub fn metadata(&self) -> Result<operations::VaultMetadata> {
+        let inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::get_vault_metadata(conn)
+    }
+
+    /// Изменяет сведения о хранилище и возвращает их новое состояние
+    pub fn update_metadata(
+        &self,
+        update: &operations::VaultMetadataUpdate,
+    ) -> Result<operations::VaultMetadata> {
+        let mut inner = self.inner();
+
+        let conn = inner
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        operations::update_vault_metadata(conn, update)?;
+        let metadata = operations::get_vault_metadata(conn)?;
+        inner.note_changes(1);
+
+        Ok(metadata)
+    }
+
+    /// Включает автоматические резервные копии для открытого хранилища
+    pub fn enable_backups(&self, policy: backup::BackupPolicy) {
+        self.inner().backups = Some(BackupState {
+            policy,
+            changes_since_backup: 0,
+        });
+    }
+
+    /// Делает резервную копию сейчас по переданным правилам — в том числе
+    /// когда автоматические копии отключены
+    pub fn backup_now(&self, policy: &backup::BackupPolicy) -> Result<backup::BackupInfo> {
+        let mut inner = self.inner();
+        inner.take_backup(policy)
+    }
+}
+
+impl VaultInner {
+    /// Учитывает изменения и делает копию, когда их накопилось достаточно.
+    /// Ошибка копирования не отменяет уже выполненное изменение.
+    fn note_changes(&mut self, count: usize) {
+        let Some(state) = self.backups.as_mut() else {
+            return;
+        };
+
+        let count = u32::try_from(count).unwrap_or(u32::MAX);
+        state.changes_since_backup = state.changes_since_backup.saturating_add(count);
+        let every = state.policy.every_changes;
+        if every == 0 || state.changes_since_backup < every {
+            return;
+        }
+
+        let policy = state.policy.clone();
+        if let Err(e) = self.take_backup(&policy) {
+            eprintln!("Automatic backup failed: {e:#}");
+        }
+    }
+
+    fn take_backup(&mut self, policy: &backup::BackupPolicy) -> Result<backup::BackupInfo> {
+        let conn = self
+            .connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Vault is locked"))?;
+
+        let info = backup::create(conn, &self.path, policy)?;
+        if let Some(state) = self.backups.as_mut() {
+            state.changes_since_backup = 0;
+        }
+
+        Ok(info)
     }
 }
 
@@ -148,7 +640,9 @@ pub fn create_new_vault<P: Into<PathBuf>>(
         anyhow::bail!("Storage file already exists");
     }
 
-    connection::create_new_storage(&path, master_password).context("Failed to create storage")?;
+    let conn = connection::create_new_storage(&path, master_password)
+        .context("Failed to create storage")?;
+    operations::init_vault_metadata(&conn, operations::unix_now(), env!("CARGO_PKG_VERSION"))?;
 
     Ok(Vault::new(path))
 }
@@ -1,13 +1,244 @@
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection};
-use serde::Serialize;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Краткое описание сервиса (без пароля)
+use super::attachments;
+use crate::utils::card::{self, CardBrand};
+
+/// Тип записи
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryType {
+    /// Сайт или приложение: логин, пароль, адрес, TOTP
+    #[default]
+    Login,
+    /// Заметка: заголовок и текст в Markdown
+    SecureNote,
+    /// Платёжная карта
+    Card,
+    /// Личные данные: имя, адрес, документы
+    Identity,
+    /// Ключ SSH
+    SshKey,
+}
+
+impl EntryType {
+    /// Значение столбца `entry_type`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::SecureNote => "secure_note",
+            Self::Card => "card",
+            Self::Identity => "identity",
+            Self::SshKey => "ssh_key",
+        }
+    }
+
+    /// Тип по значению столбца `entry_type`
+    pub fn from_column(value: &str) -> Option<Self> {
+        match value {
+            "login" => Some(Self::Login),
+            "secure_note" => Some(Self::SecureNote),
+            "card" => Some(Self::Card),
+            "identity" => Some(Self::Identity),
+            "ssh_key" => Some(Self::SshKey),
+            _ => None,
+        }
+    }
+}
+
+impl rusqlite::types::FromSql for EntryType {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        let value = value.as_str()?;
+        Self::from_column(value).ok_or_else(|| {
+            rusqlite::types::FromSqlError::Other(format!("Unknown entry type: {value}").into())
+        })
+    }
+}
+
+/// Краткое описание записи для списка (без секретов)
 #[derive(Serialize)]
-pub struct ServiceSummary {
+pub struct EntrySummary {
     pub id: u64,
-    pub site: String,
-    pub login: String,
+    #[serde(flatten)]
+    pub details: SummaryDetails,
+}
+
+/// Поля краткого описания, свои для каждого типа записи.
+/// В JSON тип передаётся полем `entry_type`. Номера карт и документов маскируются.
+#[derive(Serialize)]
+#[serde(tag = "entry_type", rename_all = "snake_case")]
+pub enum SummaryDetails {
+    Login {
+        site: String,
+        login: String,
+    },
+    SecureNote {
+        title: String,
+    },
+    Card {
+        title: String,
+        holder: String,
+        brand: Option<CardBrand>,
+        /// Последние цифры номера: «•••• 1234»
+        number: String,
+        /// Срок действия «MM/YY»
+        expiry: Option<String>,
+    },
+    Identity {
+        title: String,
+        full_name: String,
+        /// Последние символы номера паспорта или другого документа
+        document: Option<String>,
+    },
+    SshKey {
+        title: String,
+        /// Алгоритм из открытого ключа, например `ssh-ed25519`
+        key_type: String,
+        fingerprint: String,
+        comment: String,
+    },
+}
+
+/// Заметка: заголовок и текст в Markdown
+#[derive(Serialize)]
+pub struct SecureNote {
+    pub id: u64,
+    pub title: String,
+    pub body: String,
+}
+
+/// Данные платёжной карты
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CardDetails {
+    pub holder: String,
+    /// Номер без пробелов
+    pub number: String,
+    /// Месяц окончания срока действия, 1–12
+    pub expiry_month: Option<u8>,
+    /// Год окончания срока действия, четыре цифры
+    pub expiry_year: Option<u16>,
+    pub cvv: Option<String>,
+    pub pin: Option<String>,
+}
+
+impl CardDetails {
+    /// Проверяет номер (алгоритм Луна), срок действия, CVV и PIN.
+    /// Номер приводится к виду без пробелов, пустые поля — к `None`.
+    pub fn validated(&self) -> Result<Self> {
+        let number = card::normalize_number(&self.number)
+            .filter(|number| card::check_number(number).valid)
+            .ok_or_else(|| anyhow::anyhow!("Invalid card number"))?;
+
+        if self
+            .expiry_month
+            .is_some_and(|month| !(1..=12).contains(&month))
+        {
+            anyhow::bail!("Expiry month must be between 1 and 12");
+        }
+        if self
+            .expiry_year
+            .is_some_and(|year| !(2000..=2099).contains(&year))
+        {
+            anyhow::bail!("Expiry year must have four digits");
+        }
+
+        let cvv = non_empty_digits(self.cvv.as_deref(), 3..=4, "CVV")?;
+        let pin = non_empty_digits(self.pin.as_deref(), 4..=12, "PIN")?;
+
+        Ok(Self {
+            holder: self.holder.trim().to_string(),
+            number,
+            expiry_month: self.expiry_month,
+            expiry_year: self.expiry_year,
+            cvv,
+            pin,
+        })
+    }
+
+    /// Срок действия «MM/YY», если указаны месяц и год
+    fn expiry(&self) -> Option<String> {
+        Some(format!(
+            "{:02}/{:02}",
+            self.expiry_month?,
+            self.expiry_year? % 100
+        ))
+    }
+}
+
+/// Личные данные
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdentityDetails {
+    pub full_name: String,
+    pub address: Option<String>,
+    pub phone: Option<String>,
+    pub passport_number: Option<String>,
+    /// Номер другого удостоверения личности
+    pub id_number: Option<String>,
+}
+
+impl IdentityDetails {
+    /// Убирает пробелы по краям; пустые поля становятся `None`
+    pub fn normalized(&self) -> Self {
+        let trimmed = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        Self {
+            full_name: self.full_name.trim().to_string(),
+            address: trimmed(&self.address),
+            phone: trimmed(&self.phone),
+            passport_number: trimmed(&self.passport_number),
+            id_number: trimmed(&self.id_number),
+        }
+    }
+}
+
+/// Ключ SSH: закрытый ключ в формате OpenSSH и вычисленные из него сведения
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SshKeyDetails {
+    pub private_key: String,
+    /// Открытый ключ в формате `authorized_keys`
+    pub public_key: String,
+    /// Отпечаток SHA-256, как его показывает `ssh-keygen -l`
+    pub fingerprint: String,
+    pub comment: String,
+}
+
+/// Платёжная карта со всеми данными
+#[derive(Serialize)]
+pub struct Card {
+    pub id: u64,
+    pub title: String,
+    pub brand: Option<CardBrand>,
+    #[serde(flatten)]
+    pub details: CardDetails,
+}
+
+/// Личные данные со всеми полями
+#[derive(Serialize)]
+pub struct Identity {
+    pub id: u64,
+    pub title: String,
+    #[serde(flatten)]
+    pub details: IdentityDetails,
+}
+
+/// Ключ SSH со всеми данными
+#[derive(Serialize)]
+pub struct SshKey {
+    pub id: u64,
+    pub title: String,
+    #[serde(flatten)]
+    pub details: SshKeyDetails,
 }
 
 /// Полная запись (включая пароль)
@@ -19,20 +250,98 @@ pub struct PasswordEntry {
     pub password: String,
 }
 
-/// Возвращает список всех сервисов (без паролей)
-pub fn list_services(conn: &Connection) -> Result<Vec<ServiceSummary>> {
+/// Запись со всеми хранимыми полями (для экспорта и импорта)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntryRecord {
+    pub entry_type: EntryType,
+    /// Название сайта или заголовок записи другого типа
+    pub site: String,
+    pub login: String,
+    pub password: String,
+    pub url: Option<String>,
+    /// Заметки к записи или текст заметки
+    pub notes: Option<String>,
+    pub folder: Option<String>,
+    /// Секрет TOTP (base32 или otpauth:// URI)
+    pub totp: Option<String>,
+    pub custom_fields: Vec<CustomField>,
+    /// Прежние пароли, от старых к новым
+    pub history: Vec<PasswordHistoryItem>,
+    pub attachments: Vec<AttachmentRecord>,
+    /// Данные карты для записей типа `card`
+    pub card: Option<CardDetails>,
+    /// Личные данные для записей типа `identity`
+    pub identity: Option<IdentityDetails>,
+    /// Ключ для записей типа `ssh_key`
+    pub ssh_key: Option<SshKeyDetails>,
+    /// Время создания, секунды Unix
+    pub created_at: i64,
+    /// Время последнего изменения, секунды Unix
+    pub updated_at: i64,
+}
+
+/// Пользовательское поле записи
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomField {
+    pub name: String,
+    pub value: String,
+    /// Скрывать ли значение в интерфейсе
+    pub hidden: bool,
+}
+
+/// Прежний пароль записи
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordHistoryItem {
+    pub password: String,
+    /// Когда пароль был заменён, секунды Unix
+    pub changed_at: i64,
+}
+
+/// Вложенный файл записи
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttachmentRecord {
+    pub name: String,
+    #[serde(with = "base64_bytes")]
+    pub data: Vec<u8>,
+    /// Время добавления, секунды Unix
+    pub created_at: i64,
+}
+
+/// Сериализация двоичных данных строкой base64 вместо массива чисел
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Текущее время в секундах Unix
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Возвращает краткие описания всех записей (без секретов)
+pub fn list_services(conn: &Connection) -> Result<Vec<EntrySummary>> {
     let mut stmt = conn
-        .prepare("SELECT id, site, login FROM passwords")
+        .prepare(&format!("{ENTRY_SUMMARY_SELECT} ORDER BY p.id"))
         .context("Failed to prepare service list query")?;
 
     let rows = stmt
-        .query_map([], |row| {
-            Ok(ServiceSummary {
-                id: row.get(0)?,
-                site: row.get(1)?,
-                login: row.get(2)?,
-            })
-        })
+        .query_map([], entry_summary_from_row)
         .context("Failed to execute service list query")?;
 
     let mut services = Vec::new();
@@ -43,10 +352,42 @@ pub fn list_services(conn: &Connection) -> Result<Vec<ServiceSummary>> {
     Ok(services)
 }
 
+/// Ищет сервисы по вхождению строки в название, логин или адрес (без учёта регистра ASCII)
+pub fn search_services(conn: &Connection, query: &str) -> Result<Vec<EntrySummary>> {
+    let pattern = format!(
+        "%{}%",
+        query
+            .trim()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+    let mut stmt = conn
+        .prepare(&format!(
+            "{ENTRY_SUMMARY_SELECT}
+             WHERE p.site LIKE ?1 ESCAPE '\\'
+                OR p.login LIKE ?1 ESCAPE '\\'
+                OR p.url LIKE ?1 ESCAPE '\\'
+             ORDER BY p.site COLLATE NOCASE, p.login COLLATE NOCASE"
+        ))
+        .context("Failed to prepare service search query")?;
+
+    let rows = stmt
+        .query_map([pattern], entry_summary_from_row)
+        .context("Failed to execute service search query")?;
+
+    let mut services = Vec::new();
+    for row in rows {
+        services.push(row.context("Failed to parse service entry")?);
+    }
+
+    Ok(services)
+}
+
 /// Возвращает пароль по ID
 pub fn get_password(conn: &Connection, id: u64) -> Result<PasswordEntry> {
     conn.query_row(
-        "SELECT id, site, login, password FROM passwords WHERE id = ?1",
+        "SELECT id, site, login, password FROM passwords WHERE id = ?1 AND entry_type = 'login'",
         params![id],
         |row| {
             Ok(PasswordEntry {
@@ -62,9 +403,11 @@ pub fn get_password(conn: &Connection, id: u64) -> Result<PasswordEntry> {
 
 /// Добавляет новую запись
 pub fn add_password(conn: &Connection, site: &str, login: &str, password: &str) -> Result<u64> {
+    let now = unix_now();
     conn.execute(
-        "INSERT INTO passwords (site, login, password) VALUES (?1, ?2, ?3)",
-        params![site, login, password],
+        "INSERT INTO passwords (site, login, password, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?4)",
+        params![site, login, password, now],
     )
     .context("Failed to insert new password")?;
 
@@ -78,3 +421,879 @@ pub fn delete_password(conn: &Connection, id: u64) -> Result<()> {
 
     Ok(())
 }
+
+/// Добавляет заметку
+pub fn add_secure_note(conn: &Connection, title: &str, body: &str) -> Result<u64> {
+    let now = unix_now();
+    conn.execute(
+        "INSERT INTO passwords (entry_type, site, login, password, notes, created_at, updated_at)
+         VALUES (?1, ?2, '', '', ?3, ?4, ?4)",
+        params![EntryType::SecureNote.as_str(), title, body, now],
+    )
+    .context("Failed to insert secure note")?;
+
+    Ok(conn.last_insert_rowid() as u64)
+}
+
+/// Возвращает заметку по ID
+pub fn get_secure_note(conn: &Connection, id: u64) -> Result<SecureNote> {
+    conn.query_row(
+        "SELECT id, site, notes FROM passwords WHERE id = ?1 AND entry_type = ?2",
+        params![id, EntryType::SecureNote.as_str()],
+        |row| {
+            Ok(SecureNote {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                body: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            })
+        },
+    )
+    .optional()
+    .context("Failed to fetch secure note")?
+    .with_context(|| format!("Secure note {id} not found"))
+}
+
+/// Изменяет заголовок и текст заметки
+pub fn update_secure_note(conn: &Connection, id: u64, title: &str, body: &str) -> Result<()> {
+    let updated = conn
+        .execute(
+            "UPDATE passwords SET site = ?1, notes = ?2, updated_at = ?3
+             WHERE id = ?4 AND entry_type = ?5",
+            params![title, body, unix_now(), id, EntryType::SecureNote.as_str()],
+        )
+        .context("Failed to update secure note")?;
+    if updated == 0 {
+        anyhow::bail!("Secure note {} not found", id);
+    }
+
+    Ok(())
+}
+
+/// Добавляет платёжную карту; данные карты должны быть проверены заранее
+pub fn add_card(conn: &Connection, title: &str, details: &CardDetails) -> Result<u64> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+    let id = insert_titled_entry(&tx, EntryType::Card, title)?;
+    insert_card_details(&tx, id, details)?;
+    tx.commit().context("Failed to commit card")?;
+
+    Ok(id)
+}
+
+/// Возвращает платёжную карту по ID
+pub fn get_card(conn: &Connection, id: u64) -> Result<Card> {
+    let title = get_entry_title(conn, id, EntryType::Card)?;
+    let details = get_card_details(conn, id)?.unwrap_or_default();
+
+    Ok(Card {
+        id,
+        title,
+        brand: card::detect_brand(&details.number),
+        details,
+    })
+}
+
+/// Изменяет платёжную карту; данные карты должны быть проверены заранее
+pub fn update_card(conn: &Connection, id: u64, title: &str, details: &CardDetails) -> Result<()> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+    update_entry_title(&tx, id, EntryType::Card, title)?;
+    insert_card_details(&tx, id, details)?;
+    tx.commit().context("Failed to commit card")?;
+
+    Ok(())
+}
+
+/// Добавляет личные данные
+pub fn add_identity(conn: &Connection, title: &str, details: &IdentityDetails) -> Result<u64> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+    let id = insert_titled_entry(&tx, EntryType::Identity, title)?;
+    insert_identity_details(&tx, id, details)?;
+    tx.commit().context("Failed to commit identity")?;
+
+    Ok(id)
+}
+
+/// Возвращает личные данные по ID
+pub fn get_identity(conn: &Connection, id: u64) -> Result<Identity> {
+    let title = get_entry_title(conn, id, EntryType::Identity)?;
+    let details = get_identity_details(conn, id)?.unwrap_or_default();
+
+    Ok(Identity { id, title, details })
+}
+
+/// Изменяет личные данные
+pub fn update_identity(
+    conn: &Connection,
+    id: u64,
+    title: &str,
+    details: &IdentityDetails,
+) -> Result<()> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+    update_entry_title(&tx, id, EntryType::Identity, title)?;
+    insert_identity_details(&tx, id, details)?;
+    tx.commit().context("Failed to commit identity")?;
+
+    Ok(())
+}
+
+/// Добавляет ключ SSH; сведения о ключе должны быть получены из закрытого ключа
+pub fn add_ssh_key(conn: &Connection, title: &str, details: &SshKeyDetails) -> Result<u64> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+    let id = insert_titled_entry(&tx, EntryType::SshKey, title)?;
+    insert_ssh_key_details(&tx, id, details)?;
+    tx.commit().context("Failed to commit SSH key")?;
+
+    Ok(id)
+}
+
+/// Возвращает ключ SSH по ID
+pub fn get_ssh_key(conn: &Connection, id: u64) -> Result<SshKey> {
+    let title = get_entry_title(conn, id, EntryType::SshKey)?;
+    let details = get_ssh_key_details(conn, id)?.unwrap_or_default();
+
+    Ok(SshKey { id, title, details })
+}
+
+/// Возвращает все ключи SSH хранилища
+pub fn list_ssh_keys(conn: &Connection) -> Result<Vec<SshKey>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.id, p.site, k.private_key, k.public_key, k.fingerprint, k.comment
+             FROM passwords p JOIN ssh_keys k ON k.entry_id = p.id
+             WHERE p.entry_type = ?1 ORDER BY p.id",
+        )
+        .context("Failed to prepare SSH key query")?;
+
+    let rows = stmt
+        .query_map(params![EntryType::SshKey.as_str()], |row| {
+            Ok(SshKey {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                details: SshKeyDetails {
+                    private_key: row.get(2)?,
+                    public_key: row.get(3)?,
+                    fingerprint: row.get(4)?,
+                    comment: row.get(5)?,
+                },
+            })
+        })
+        .context("Failed to execute SSH key query")?;
+
+    let mut keys = Vec::new();
+    for row in rows {
+        keys.push(row.context("Failed to parse SSH key")?);
+    }
+
+    Ok(keys)
+}
+
+/// Добавляет запись без логина и пароля, у которой есть только заголовок
+fn insert_titled_entry(conn: &Connection, entry_type: EntryType, title: &str) -> Result<u64> {
+    let now = unix_now();
+    conn.execute(
+        "INSERT INTO passwords (entry_type, site, login, password, created_at, updated_at)
+         VALUES (?1, ?2, '', '', ?3, ?3)",
+        params![entry_type.as_str(), title, now],
+    )
+    .context("Failed to insert entry")?;
+
+    Ok(conn.last_insert_rowid() as u64)
+}
+
+fn get_entry_title(conn: &Connection, id: u64, entry_type: EntryType) -> Result<String> {
+    conn.query_row(
+        "SELECT site FROM passwords WHERE id = ?1 AND entry_type = ?2",
+        params![id, entry_type.as_str()],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("Failed to fetch entry")?
+    .with_context(|| format!("Entry {id} of type {} not found", entry_type.as_str()))
+}
+
+fn update_entry_title(
+    conn: &Connection,
+    id: u64,
+    entry_type: EntryType,
+    title: &str,
+) -> Result<()> {
+    let updated = conn
+        .execute(
+            "UPDATE passwords SET site = ?1, updated_at = ?2 WHERE id = ?3 AND entry_type = ?4",
+            params![title, unix_now(), id, entry_type.as_str()],
+        )
+        .context("Failed to update entry")?;
+    if updated == 0 {
+        anyhow::bail!("Entry {} of type {} not found", id, entry_type.as_str());
+    }
+
+    Ok(())
+}
+
+fn insert_card_details(conn: &Connection, id: u64, details: &CardDetails) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO cards
+         (entry_id, holder, number, expiry_month, expiry_year, cvv, pin)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            id,
+            details.holder,
+            details.number,
+            details.expiry_month,
+            details.expiry_year,
+            details.cvv,
+            details.pin,
+        ],
+    )
+    .context("Failed to save card")?;
+
+    Ok(())
+}
+
+fn get_card_details(conn: &Connection, id: u64) -> Result<Option<CardDetails>> {
+    conn.query_row(
+        "SELECT holder, number, expiry_month, expiry_year, cvv, pin FROM cards WHERE entry_id = ?1",
+        params![id],
+        |row| {
+            Ok(CardDetails {
+                holder: row.get(0)?,
+                number: row.get(1)?,
+                expiry_month: row.get(2)?,
+                expiry_year: row.get(3)?,
+                cvv: row.get(4)?,
+                pin: row.get(5)?,
+            })
+        },
+    )
+    .optional()
+    .context("Failed to fetch card")
+}
+
+fn insert_identity_details(conn: &Connection, id: u64, details: &IdentityDetails) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO identities
+         (entry_id, full_name, address, phone, passport_number, id_number)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            id,
+            details.full_name,
+            details.address,
+            details.phone,
+            details.passport_number,
+            details.id_number,
+        ],
+    )
+    .context("Failed to save identity")?;
+
+    Ok(())
+}
+
+fn insert_ssh_key_details(conn: &Connection, id: u64, details: &SshKeyDetails) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO ssh_keys (entry_id, private_key, public_key, fingerprint, comment)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            id,
+            details.private_key,
+            details.public_key,
+            details.fingerprint,
+            details.comment,
+        ],
+    )
+    .context("Failed to save SSH key")?;
+
+    Ok(())
+}
+
+fn get_ssh_key_details(conn: &Connection, id: u64) -> Result<Option<SshKeyDetails>> {
+    conn.query_row(
+        "SELECT private_key, public_key, fingerprint, comment FROM ssh_keys WHERE entry_id = ?1",
+        params![id],
+        |row| {
+            Ok(SshKeyDetails {
+                private_key: row.get(0)?,
+                public_key: row.get(1)?,
+                fingerprint: row.get(2)?,
+                comment: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .context("Failed to fetch SSH key")
+}
+
+fn get_identity_details(conn: &Connection, id: u64) -> Result<Option<IdentityDetails>> {
+    conn.query_row(
+        "SELECT full_name, address, phone, passport_number, id_number
+         FROM identities WHERE entry_id = ?1",
+        params![id],
+        |row| {
+            Ok(IdentityDetails {
+                full_name: row.get(0)?,
+                address: row.get(1)?,
+                phone: row.get(2)?,
+                passport_number: row.get(3)?,
+                id_number: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+    .context("Failed to fetch identity")
+}
+
+/// Проверяет необязательное поле из цифр заданной длины; пустое значение — `None`
+fn non_empty_digits(
+    value: Option<&str>,
+    len: std::ops::RangeInclusive<usize>,
+    name: &str,
+) -> Result<Option<String>> {
+    let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(None);
+    };
+    if !len.contains(&value.len()) || !value.bytes().all(|b| b.is_ascii_digit()) {
+        anyhow::bail!("{} must have {}–{} digits", name, len.start(), len.end());
+    }
+
+    Ok(Some(value.to_string()))
+}
+
+/// Возвращает все записи со всеми полями
+pub fn list_entry_records(conn: &Connection) -> Result<Vec<EntryRecord>> {
+    Ok(list_records(conn, true)?
+        .into_iter()
+        .map(|(_, record)| record)
+        .collect())
+}
+
+/// Возвращает все записи вместе с их ID, но без вложений: аудиту и поиску
+/// дубликатов при импорте содержимое файлов не нужно, а читать его долго
+pub fn list_identified_records(conn: &Connection) -> Result<Vec<(u64, EntryRecord)>> {
+    list_records(conn, false)
+}
+
+fn list_records(conn: &Connection, with_attachments: bool) -> Result<Vec<(u64, EntryRecord)>> {
+    let mut stmt = conn
+        .prepare(&format!("{ENTRY_RECORD_SELECT} ORDER BY id"))
+        .context("Failed to prepare entry export query")?;
+
+    let rows = stmt
+        .query_map([], entry_record_from_row)
+        .context("Failed to execute entry export query")?;
+
+    let mut records = Vec::new();
+    for row in rows {
+        let (id, mut record) = row.context("Failed to parse entry")?;
+        load_entry_children(conn, id, &mut record)?;
+        if with_attachments {
+            record.attachments = list_attachment_records(conn, id)?;
+        }
+        records.push((id, record));
+    }
+
+    Ok(records)
+}
+
+/// Возвращает записи с указанными ID со всеми полями в том же порядке
+pub fn get_entry_records(conn: &Connection, ids: &[u64]) -> Result<Vec<EntryRecord>> {
+    let mut stmt = conn
+        .prepare(&format!("{ENTRY_RECORD_SELECT} WHERE id = ?1"))
+        .context("Failed to prepare entry query")?;
+
+    let mut records = Vec::with_capacity(ids.len());
+    for &id in ids {
+        let (_, mut record) = stmt
+            .query_row(params![id], entry_record_from_row)
+            .optional()
+            .context("Failed to read entry")?
+            .with_context(|| format!("Entry {id} not found"))?;
+        load_entry_children(conn, id, &mut record)?;
+        record.attachments = list_attachment_records(conn, id)?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Удаляет записи с указанными ID в одной транзакции; если какой-то записи нет,
+/// ничего не удаляется
+pub fn delete_entry_records(conn: &Connection, ids: &[u64]) -> Result<()> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+
+    for &id in ids {
+        let deleted = tx
+            .execute("DELETE FROM passwords WHERE id = ?1", params![id])
+            .context("Failed to delete password entry")?;
+        if deleted == 0 {
+            anyhow::bail!("Entry {id} not found");
+        }
+    }
+
+    tx.commit().context("Failed to commit deletion")?;
+
+    Ok(())
+}
+
+const ENTRY_SUMMARY_SELECT: &str = "SELECT p.id, p.entry_type, p.site, p.login,
+            c.holder, c.number, c.expiry_month, c.expiry_year,
+            i.full_name, i.passport_number, i.id_number,
+            k.public_key, k.fingerprint, k.comment
+     FROM passwords p
+     LEFT JOIN cards c ON c.entry_id = p.id
+     LEFT JOIN identities i ON i.entry_id = p.id
+     LEFT JOIN ssh_keys k ON k.entry_id = p.id";
+
+fn entry_summary_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<EntrySummary> {
+    let site: String = row.get(2)?;
+    let details = match row.get(1)? {
+        EntryType::Login => SummaryDetails::Login {
+            site,
+            login: row.get(3)?,
+        },
+        EntryType::SecureNote => SummaryDetails::SecureNote { title: site },
+        EntryType::Card => {
+            let details = CardDetails {
+                holder: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                number: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                expiry_month: row.get(6)?,
+                expiry_year: row.get(7)?,
+                ..CardDetails::default()
+            };
+            SummaryDetails::Card {
+                title: site,
+                brand: card::detect_brand(&details.number),
+                number: card::mask_tail(&details.number),
+                expiry: details.expiry(),
+                holder: details.holder,
+            }
+        }
+        EntryType::Identity => {
+            let passport: Option<String> = row.get(9)?;
+            let id_number: Option<String> = row.get(10)?;
+            SummaryDetails::Identity {
+                title: site,
+                full_name: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+                document: passport.or(id_number).as_deref().map(card::mask_tail),
+            }
+        }
+        EntryType::SshKey => {
+            let public_key: Option<String> = row.get(11)?;
+            SummaryDetails::SshKey {
+                title: site,
+                key_type: public_key
+                    .as_deref()
+                    .and_then(|key| key.split_whitespace().next())
+                    .unwrap_or_default()
+                    .to_string(),
+                fingerprint: row.get::<_, Option<String>>(12)?.unwrap_or_default(),
+                comment: row.get::<_, Option<String>>(13)?.unwrap_or_default(),
+            }
+        }
+    };
+
+    Ok(EntrySummary {
+        id: row.get(0)?,
+        details,
+    })
+}
+
+const ENTRY_RECORD_SELECT: &str =
+    "SELECT id, entry_type, site, login, password, url, notes, folder, totp, created_at, updated_at
+     FROM passwords";
+
+fn entry_record_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<(u64, EntryRecord)> {
+    Ok((
+        row.get(0)?,
+        EntryRecord {
+            entry_type: row.get(1)?,
+            site: row.get(2)?,
+            login: row.get(3)?,
+            password: row.get(4)?,
+            url: row.get(5)?,
+            notes: row.get(6)?,
+            folder: row.get(7)?,
+            totp: row.get(8)?,
+            custom_fields: Vec::new(),
+            history: Vec::new(),
+            attachments: Vec::new(),
+            card: None,
+            identity: None,
+            ssh_key: None,
+            created_at: row.get(9)?,
+            updated_at: row.get(10)?,
+        },
+    ))
+}
+
+fn load_entry_children(conn: &Connection, id: u64, record: &mut EntryRecord) -> Result<()> {
+    record.custom_fields = list_custom_fields(conn, id)?;
+    record.history = list_password_history(conn, id)?;
+    record.card = get_card_details(conn, id)?;
+    record.identity = get_identity_details(conn, id)?;
+    record.ssh_key = get_ssh_key_details(conn, id)?;
+    Ok(())
+}
+
+/// Возвращает пользовательские поля записи
+pub fn list_custom_fields(conn: &Connection, entry_id: u64) -> Result<Vec<CustomField>> {
+    let mut stmt = conn
+        .prepare("SELECT name, value, hidden FROM custom_fields WHERE entry_id = ?1 ORDER BY id")
+        .context("Failed to prepare custom field query")?;
+
+    let rows = stmt
+        .query_map(params![entry_id], |row| {
+            Ok(CustomField {
+                name: row.get(0)?,
+                value: row.get(1)?,
+                hidden: row.get(2)?,
+            })
+        })
+        .context("Failed to execute custom field query")?;
+
+    let mut fields = Vec::new();
+    for row in rows {
+        fields.push(row.context("Failed to parse custom field")?);
+    }
+
+    Ok(fields)
+}
+
+/// Возвращает историю паролей записи, от старых к новым
+pub fn list_password_history(conn: &Connection, entry_id: u64) -> Result<Vec<PasswordHistoryItem>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT password, changed_at FROM password_history
+             WHERE entry_id = ?1 ORDER BY changed_at, id",
+        )
+        .context("Failed to prepare password history query")?;
+
+    let rows = stmt
+        .query_map(params![entry_id], |row| {
+            Ok(PasswordHistoryItem {
+                password: row.get(0)?,
+                changed_at: row.get(1)?,
+            })
+        })
+        .context("Failed to execute password history query")?;
+
+    let mut history = Vec::new();
+    for row in rows {
+        history.push(row.context("Failed to parse password history item")?);
+    }
+
+    Ok(history)
+}
+
+/// Возвращает вложения записи вместе с содержимым
+pub fn list_attachment_records(conn: &Connection, entry_id: u64) -> Result<Vec<AttachmentRecord>> {
+    let mut stmt = conn
+        .prepare("SELECT name, data, created_at FROM attachments WHERE entry_id = ?1 ORDER BY id")
+        .context("Failed to prepare attachment query")?;
+
+    let rows = stmt
+        .query_map(params![entry_id], |row| {
+            Ok(AttachmentRecord {
+                name: row.get(0)?,
+                data: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })
+        .context("Failed to execute attachment query")?;
+
+    let mut attachments = Vec::new();
+    for row in rows {
+        attachments.push(row.context("Failed to parse attachment")?);
+    }
+
+    Ok(attachments)
+}
+
+/// Добавляет запись со всеми полями, сохраняя её метки времени
+pub fn insert_entry_record(conn: &Connection, record: &EntryRecord) -> Result<u64> {
+    conn.execute(
+        "INSERT INTO passwords
+         (site, login, password, url, notes, folder, totp, created_at, updated_at, entry_type)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            record.site,
+            record.login,
+            record.password,
+            record.url,
+            record.notes,
+            record.folder,
+            record.totp,
+            record.created_at,
+            record.updated_at,
+            record.entry_type.as_str(),
+        ],
+    )
+    .context("Failed to insert entry")?;
+    let id = conn.last_insert_rowid() as u64;
+
+    insert_entry_children(conn, id, record)?;
+
+    Ok(id)
+}
+
+/// Заменяет все поля существующей записи, включая историю и вложения
+pub fn replace_entry_record(conn: &Connection, id: u64, record: &EntryRecord) -> Result<()> {
+    let updated = conn
+        .execute(
+            "UPDATE passwords SET site = ?1, login = ?2, password = ?3, url = ?4, notes = ?5,
+             folder = ?6, totp = ?7, created_at = ?8, updated_at = ?9, entry_type = ?10
+             WHERE id = ?11",
+            params![
+                record.site,
+                record.login,
+                record.password,
+                record.url,
+                record.notes,
+                record.folder,
+                record.totp,
+                record.created_at,
+                record.updated_at,
+                record.entry_type.as_str(),
+                id,
+            ],
+        )
+        .context("Failed to update entry")?;
+    if updated == 0 {
+        anyhow::bail!("Entry {} not found", id);
+    }
+
+    for table in [
+        "custom_fields",
+        "password_history",
+        "attachments",
+        "cards",
+        "identities",
+        "ssh_keys",
+    ] {
+        conn.execute(
+            &format!("DELETE FROM {table} WHERE entry_id = ?1"),
+            params![id],
+        )
+        .with_context(|| format!("Failed to clear {table}"))?;
+    }
+
+    insert_entry_children(conn, id, record)
+}
+
+/// Добавляет пользовательские поля, историю, вложения и данные записей других типов
+fn insert_entry_children(conn: &Connection, id: u64, record: &EntryRecord) -> Result<()> {
+    for field in &record.custom_fields {
+        conn.execute(
+            "INSERT INTO custom_fields (entry_id, name, value, hidden) VALUES (?1, ?2, ?3, ?4)",
+            params![id, field.name, field.value, field.hidden],
+        )
+        .context("Failed to insert custom field")?;
+    }
+
+    for item in &record.history {
+        conn.execute(
+            "INSERT INTO password_history (entry_id, password, changed_at) VALUES (?1, ?2, ?3)",
+            params![id, item.password, item.changed_at],
+        )
+        .context("Failed to insert password history item")?;
+    }
+
+    for attachment in &record.attachments {
+        attachments::ensure_size(attachment.data.len() as u64)
+            .with_context(|| format!("Failed to insert attachment {:?}", attachment.name))?;
+        conn.execute(
+            "INSERT INTO attachments (entry_id, name, size, data, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                id,
+                attachment.name,
+                attachment.data.len() as i64,
+                attachment.data,
+                attachment.created_at
+            ],
+        )
+        .context("Failed to insert attachment")?;
+    }
+
+    if let Some(card) = &record.card {
+        insert_card_details(conn, id, card)?;
+    }
+    if let Some(identity) = &record.identity {
+        insert_identity_details(conn, id, identity)?;
+    }
+    if let Some(ssh_key) = &record.ssh_key {
+        insert_ssh_key_details(conn, id, ssh_key)?;
+    }
+
+    Ok(())
+}
+
+/// Добавляет набор записей в одной транзакции: либо все, либо ни одной.
+/// Не может быть вызвана внутри уже открытой транзакции.
+pub fn insert_entry_records(conn: &Connection, records: &[EntryRecord]) -> Result<Vec<u64>> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+
+    let mut ids = Vec::with_capacity(records.len());
+    for record in records {
+        ids.push(insert_entry_record(&tx, record)?);
+    }
+
+    tx.commit().context("Failed to commit entries")?;
+
+    Ok(ids)
+}
+
+/// Добавляет новые записи и заменяет существующие в одной транзакции.
+/// Не может быть вызвана внутри уже открытой транзакции.
+pub fn apply_entry_records(
+    conn: &Connection,
+    new_records: &[EntryRecord],
+    replacements: &[(u64, EntryRecord)],
+) -> Result<Vec<u64>> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+
+    for (id, record) in replacements {
+        replace_entry_record(&tx, *id, record)?;
+    }
+
+    let mut ids = Vec::with_capacity(new_records.len());
+    for record in new_records {
+        ids.push(insert_entry_record(&tx, record)?);
+    }
+
+    tx.commit().context("Failed to commit entries")?;
+
+    Ok(ids)
+}
+
+/// Сведения о хранилище
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultMetadata {
+    /// Отображаемое имя; пустое — показывать имя файла
+    pub display_name: String,
+    pub description: Option<String>,
+    /// Цвет в виде `#rrggbb`
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    /// Время создания, секунды Unix; `None` для старых пустых хранилищ
+    pub created_at: Option<i64>,
+    /// Версия приложения, создавшего хранилище
+    pub app_version: Option<String>,
+    pub schema_version: u32,
+}
+
+/// Изменяемые пользователем сведения о хранилище
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct VaultMetadataUpdate {
+    pub display_name: String,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+/// Максимальная длина отображаемого имени и значка в символах
+const MAX_LABEL_LEN: usize = 100;
+/// Максимальная длина описания в символах
+const MAX_DESCRIPTION_LEN: usize = 2000;
+
+/// Записывает время создания и версию приложения в новое хранилище
+pub fn init_vault_metadata(conn: &Connection, created_at: i64, app_version: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE vault_metadata SET created_at = ?1, app_version = ?2 WHERE id = 1",
+        params![created_at, app_version],
+    )
+    .context("Failed to initialize vault metadata")?;
+
+    Ok(())
+}
+
+/// Возвращает сведения о хранилище
+pub fn get_vault_metadata(conn: &Connection) -> Result<VaultMetadata> {
+    let schema_version: u32 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .context("Failed to read schema version")?;
+
+    conn.query_row(
+        "SELECT display_name, description, color, icon, created_at, app_version
+         FROM vault_metadata WHERE id = 1",
+        [],
+        |row| {
+            Ok(VaultMetadata {
+                display_name: row.get(0)?,
+                description: row.get(1)?,
+                color: row.get(2)?,
+                icon: row.get(3)?,
+                created_at: row.get(4)?,
+                app_version: row.get(5)?,
+                schema_version,
+            })
+        },
+    )
+    .context("Failed to read vault metadata")
+}
+
+/// Изменяет отображаемое имя, описание, цвет и значок хранилища
+pub fn update_vault_metadata(conn: &Connection, update: &VaultMetadataUpdate) -> Result<()> {
+    let display_name = update.display_name.trim();
+    if display_name.chars().count() > MAX_LABEL_LEN {
+        anyhow::bail!("Display name is longer than {} characters", MAX_LABEL_LEN);
+    }
+    let description = update
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    if description.is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_LEN) {
+        anyhow::bail!(
+            "Description is longer than {} characters",
+            MAX_DESCRIPTION_LEN
+        );
+    }
+    let color = update
+        .color
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    if color.is_some_and(|c| !is_hex_color(c)) {
+        anyhow::bail!("Color must be in #rrggbb format");
+    }
+    let icon = update
+        .icon
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    if icon.is_some_and(|i| i.chars().count() > MAX_LABEL_LEN) {
+        anyhow::bail!("Icon is longer than {} characters", MAX_LABEL_LEN);
+    }
+
+    conn.execute(
+        "UPDATE vault_metadata
+         SET display_name = ?1, description = ?2, color = ?3, icon = ?4
+         WHERE id = 1",
+        params![display_name, description, color, icon],
+    )
+    .context("Failed to update vault metadata")?;
+
+    Ok(())
+}
+
+/// Цвет в виде `#rrggbb`
+fn is_hex_color(value: &str) -> bool {
+    value
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
@@ -0,0 +1,348 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::operations::VaultMetadata;
+use crate::utils::settings::VaultLabel;
+
+/// Расширение файлов хранилищ
+pub const VAULT_EXTENSION: &str = "db";
+/// Максимальная длина имени хранилища в символах
+const MAX_NAME_LEN: usize = 100;
+/// Символы, недопустимые в именах файлов Windows
+const FORBIDDEN_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+/// Имена устройств Windows, которые нельзя использовать как имя файла
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Откуда хранилище попало в список
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultSource {
+    /// Основная папка хранилищ из настроек
+    Default,
+    /// Дополнительная папка, например общий сетевой диск
+    Folder,
+    /// Отдельно добавленный файл
+    File,
+}
+
+impl From<&VaultMetadata> for VaultLabel {
+    fn from(metadata: &VaultMetadata) -> Self {
+        Self {
+            display_name: metadata.display_name.clone(),
+            color: metadata.color.clone(),
+            icon: metadata.icon.clone(),
+        }
+    }
+}
+
+/// Хранилище в списке выбора
+#[derive(Debug, Clone, Serialize)]
+pub struct ListedVault {
+    /// Идентификатор для `open_vault`: имя для основной папки, иначе полный путь
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub source: VaultSource,
+    /// Папка или файл из настроек, через которые найдено хранилище
+    pub location: String,
+    /// Время последнего открытия, секунды Unix
+    pub last_opened: Option<i64>,
+    /// Размер файла; `None`, если файл сейчас недоступен
+    pub size: Option<u64>,
+    /// Сведения, сохранённые при последнем открытии
+    pub label: Option<VaultLabel>,
+}
+
+/// Известные приложению хранилища: файлы `<name>.db` в основной и дополнительных
+/// папках и отдельные файлы, которые пользователь явно добавил
+pub struct VaultRegistry {
+    folder: PathBuf,
+    folders: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+impl VaultRegistry {
+    pub fn new<P: Into<PathBuf>>(folder: P, folders: Vec<PathBuf>, files: Vec<PathBuf>) -> Self {
+        Self {
+            folder: folder.into(),
+            folders,
+            files,
+        }
+    }
+
+    /// Путь к хранилищу по имени в основной папке, по пути к `<name>.db`
+    /// в дополнительной папке или по пути добавленного файла.
+    /// Любые другие пути отклоняются.
+    pub fn resolve(&self, vault: &str) -> Result<PathBuf> {
+        let path = Path::new(vault);
+        if self.files.iter().any(|file| file == path) {
+            return Ok(path.to_path_buf());
+        }
+
+        let in_folder = path
+            .parent()
+            .is_some_and(|parent| self.folders.iter().any(|folder| folder == parent));
+        if in_folder && path.extension().is_some_and(|ext| ext == VAULT_EXTENSION) {
+            validate_name(vault_name(path).context("Invalid vault file name")?)?;
+            return Ok(path.to_path_buf());
+        }
+
+        if path.components().count() > 1 {
+            anyhow::bail!("Vault is outside the known vault locations: {:?}", path);
+        }
+
+        self.folder_vault(vault)
+    }
+
+    /// Путь к хранилищу `<name>.db` в основной папке (файл может не существовать)
+    pub fn folder_vault(&self, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        Ok(self.folder.join(format!("{name}.{VAULT_EXTENSION}")))
+    }
+
+    /// Все хранилища из всех мест. Недоступные дополнительные папки пропускаются,
+    /// недоступные добавленные файлы остаются в списке без размера.
+    pub fn list(
+        &self,
+        last_opened: &HashMap<String, i64>,
+        labels: &HashMap<String, VaultLabel>,
+    ) -> Result<Vec<ListedVault>> {
+        let mut vaults = Vec::new();
+
+        for (name, path) in scan_folder(&self.folder)? {
+            let id = name.clone();
+            vaults.push(listed(id, name, &path, VaultSource::Default, &self.folder));
+        }
+
+        for folder in &self.folders {
+            // Сетевой диск может быть не подключён — это не ошибка списка
+            let Ok(found) = scan_folder(folder) else {
+                continue;
+            };
+            for (name, path) in found {
+                let id = path.to_string_lossy().into_owned();
+                vaults.push(listed(id, name, &path, VaultSource::Folder, folder));
+            }
+        }
+
+        for file in &self.files {
+            let id = file.to_string_lossy().into_owned();
+            let name = vault_name(file).unwrap_or(&id).to_string();
+            vaults.push(listed(id, name, file, VaultSource::File, file));
+        }
+
+        for vault in &mut vaults {
+            vault.last_opened = last_opened.get(&vault.path).copied();
+            vault.label = labels.get(&vault.path).cloned();
+        }
+
+        Ok(vaults)
+    }
+}
+
+/// Приводит путь к выбранной пользователем папке к каноническому виду
+/// для добавления в список мест хранения
+pub fn canonical_folder(path: &Path) -> Result<PathBuf> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Folder not found: {:?}", path))?;
+
+    if !path.is_dir() {
+        anyhow::bail!("Not a folder: {:?}", path);
+    }
+
+    Ok(path)
+}
+
+/// Приводит путь к выбранному пользователем файлу хранилища к каноническому виду
+/// для добавления в список мест хранения
+pub fn canonical_vault_file(path: &Path) -> Result<PathBuf> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Vault not found: {:?}", path))?;
+
+    if !path.is_file() {
+        anyhow::bail!("Vault not found: {:?}", path);
+    }
+    if path.extension().and_then(|ext| ext.to_str()) != Some(VAULT_EXTENSION) {
+        anyhow::bail!("Vault file must have the .{} extension", VAULT_EXTENSION);
+    }
+
+    Ok(path)
+}
+
+/// Проверяет имя хранилища, из которого строится имя файла `<name>.db`
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        anyhow::bail!("Vault name must not be empty");
+    }
+    if name != name.trim() {
+        anyhow::bail!("Vault name must not start or end with spaces");
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        anyhow::bail!("Vault name is longer than {} characters", MAX_NAME_LEN);
+    }
+    if name.contains("..") || name.contains(FORBIDDEN_CHARS) {
+        anyhow::bail!("Vault name must not contain path separators, '..' or any of <>:\"|?*");
+    }
+    if name.chars().any(char::is_control) {
+        anyhow::bail!("Vault name must not contain control characters");
+    }
+    // Скрытые и служебные файлы (например, `.vault.db.restore`) начинаются с точки
+    if name.starts_with('.') || name.ends_with('.') {
+        anyhow::bail!("Vault name must not start or end with a dot");
+    }
+
+    let device = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(device))
+    {
+        anyhow::bail!("Vault name {:?} is reserved by the operating system", name);
+    }
+
+    Ok(())
+}
+
+/// Хранилища `<name>.db` в папке, отсортированные по имени
+fn scan_folder(folder: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut vaults: Vec<(String, PathBuf)> = std::fs::read_dir(folder)
+        .with_context(|| format!("Failed to read vault directory: {:?}", folder))?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if !path.is_file() || path.extension()? != VAULT_EXTENSION {
+                return None;
+            }
+            let name = vault_name(&path)?.to_string();
+            validate_name(&name).ok()?;
+            Some((name, path))
+        })
+        .collect();
+
+    vaults.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(vaults)
+}
+
+fn listed(
+    id: String,
+    name: String,
+    path: &Path,
+    source: VaultSource,
+    location: &Path,
+) -> ListedVault {
+    ListedVault {
+        id,
+        name,
+        path: path.to_string_lossy().into_owned(),
+        source,
+        location: location.to_string_lossy().into_owned(),
+        last_opened: None,
+        label: None,
+        size: std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+    }
+}
+
+fn vault_name(path: &Path) -> Option<&str> {
+    path.file_stem().and_then(|stem| stem.to_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> VaultRegistry {
+        VaultRegistry::new(
+            "/home/user/vaults",
+            vec![PathBuf::from("/mnt/shared")],
+            vec![PathBuf::from("/media/usb/travel.db")],
+        )
+    }
+
+    #[test]
+    fn valid_names_are_accepted() {
+        for name in [
+            "main",
+            "Семейное",
+            "work 2024",
+            "v1.2",
+            "CONSOLE",
+            "my-vault_1",
+        ] {
+            assert!(validate_name(name).is_ok(), "{name}");
+        }
+        assert!(validate_name(&"я".repeat(MAX_NAME_LEN)).is_ok());
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        let long = "a".repeat(MAX_NAME_LEN + 1);
+        for name in [
+            "",
+            "   ",
+            " main",
+            "main ",
+            long.as_str(),
+            "..",
+            "a..b",
+            "../main",
+            "folder/main",
+            "folder\\main",
+            "C:main",
+            "what?",
+            "tab\there",
+            ".hidden",
+            "main.",
+            "con",
+            "NUL.backup",
+            "com1 .txt",
+        ] {
+            assert!(validate_name(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn names_resolve_to_default_folder() {
+        assert_eq!(
+            registry().resolve("main").unwrap(),
+            Path::new("/home/user/vaults/main.db")
+        );
+        assert!(registry().resolve("..").is_err());
+        assert!(registry().resolve("AUX").is_err());
+    }
+
+    #[test]
+    fn known_locations_resolve_to_themselves() {
+        let registry = registry();
+
+        assert_eq!(
+            registry.resolve("/media/usb/travel.db").unwrap(),
+            Path::new("/media/usb/travel.db")
+        );
+        assert_eq!(
+            registry.resolve("/mnt/shared/team.db").unwrap(),
+            Path::new("/mnt/shared/team.db")
+        );
+    }
+
+    #[test]
+    fn other_paths_are_rejected() {
+        let registry = registry();
+
+        for vault in [
+            "/mnt/shared/nested/team.db",
+            "/mnt/shared/notes.txt",
+            "/mnt/shared/.team.db",
+            "/mnt/shared/../etc/team.db",
+            "/media/usb/other.db",
+            "/home/user/vaults/main.db",
+            "vaults/main",
+        ] {
+            assert!(registry.resolve(vault).is_err(), "{vault}");
+        }
+    }
+}
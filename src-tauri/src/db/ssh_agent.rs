@@ -0,0 +1,562 @@
+use anyhow::{Context, Result};
+use signature::Signer;
+use ssh_key::{Algorithm, PrivateKey};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::JoinHandle;
+
+use super::{operations, VaultInner};
+use crate::utils;
+
+/// Наибольший размер одного сообщения агента
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+// Коды сообщений протокола ssh-agent
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+/// Ключ, которым агент собирается подписать запрос
+#[derive(Debug, Clone)]
+pub struct KeyUse {
+    pub title: String,
+    pub fingerprint: String,
+}
+
+/// Спрашивает пользователя, можно ли использовать ключ; `false` — отказ
+pub type Confirm = Arc<dyn Fn(&KeyUse) -> bool + Send + Sync>;
+
+/// Встроенный ssh-agent на Unix-сокете. Ключи читаются из открытого
+/// хранилища при каждом запросе и в памяти агента не хранятся.
+/// Подписывать умеет только ключи Ed25519.
+pub struct SshAgent {
+    socket_path: PathBuf,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    running: AtomicBool,
+    vault: Weak<Mutex<VaultInner>>,
+    confirm: Option<Confirm>,
+    /// Открытые соединения, чтобы закрыть их при остановке
+    clients: Mutex<HashMap<u64, UnixStream>>,
+    next_client: AtomicU64,
+}
+
+impl SshAgent {
+    /// Создаёт сокет в каталоге с правами 0700 и начинает принимать соединения.
+    /// Права самого сокета зависят от umask до `chmod`, поэтому доступ
+    /// ограничивает именно каталог.
+    pub(super) fn start(
+        socket_path: &Path,
+        vault: Weak<Mutex<VaultInner>>,
+        confirm: Option<Confirm>,
+    ) -> Result<Self> {
+        let directory = socket_path
+            .parent()
+            .filter(|directory| !directory.as_os_str().is_empty())
+            .with_context(|| {
+                format!("SSH agent socket path has no directory: {:?}", socket_path)
+            })?;
+        utils::fs::create_private_dir(directory)
+            .context("Failed to prepare SSH agent socket directory")?;
+
+        if let Ok(metadata) = std::fs::symlink_metadata(socket_path) {
+            // Путь задаётся в настройках: удалять можно только сокет, а не файл пользователя
+            if !metadata.file_type().is_socket() {
+                anyhow::bail!("SSH agent socket path is not a socket: {:?}", socket_path);
+            }
+            if UnixStream::connect(socket_path).is_ok() {
+                anyhow::bail!("SSH agent socket is already in use: {:?}", socket_path);
+            }
+            // Сокет остался от прошлого запуска
+            std::fs::remove_file(socket_path)
+                .with_context(|| format!("Failed to remove stale socket: {:?}", socket_path))?;
+        }
+
+        let listener = UnixListener::bind(socket_path)
+            .with_context(|| format!("Failed to bind SSH agent socket: {:?}", socket_path))?;
+        if let Err(e) =
+            std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600))
+        {
+            let _ = std::fs::remove_file(socket_path);
+            return Err(e).context("Failed to restrict SSH agent socket permissions");
+        }
+
+        let shared = Arc::new(Shared {
+            running: AtomicBool::new(true),
+            vault,
+            confirm,
+            clients: Mutex::new(HashMap::new()),
+            next_client: AtomicU64::new(0),
+        });
+
+        let accept_shared = Arc::clone(&shared);
+        let thread = std::thread::Builder::new()
+            .name("ssh-agent".into())
+            .spawn(move || accept_loop(listener, accept_shared))
+            .context("Failed to start SSH agent thread")?;
+
+        Ok(Self {
+            socket_path: socket_path.to_path_buf(),
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Путь к сокету для `SSH_AUTH_SOCK`
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+}
+
+impl Drop for SshAgent {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::SeqCst);
+
+        // Будим поток, ждущий нового соединения
+        let _ = UnixStream::connect(&self.socket_path);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+        let clients = std::mem::take(&mut *self.shared.lock_clients());
+        for stream in clients.values() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+impl Shared {
+    fn lock_clients(&self) -> std::sync::MutexGuard<'_, HashMap<u64, UnixStream>> {
+        self.clients
+            .lock()
+            .expect("Failed to acquire SSH agent clients lock")
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Ключи открытого хранилища; пустой список, если оно заблокировано
+    fn keys(&self) -> Result<Vec<operations::SshKey>> {
+        let Some(vault) = self.vault.upgrade() else {
+            return Ok(Vec::new());
+        };
+        let inner = vault.lock().expect("Failed to acquire database lock");
+
+        match inner.connection.as_ref() {
+            Some(conn) => operations::list_ssh_keys(conn),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+fn accept_loop(listener: UnixListener, shared: Arc<Shared>) {
+    for stream in listener.incoming() {
+        if !shared.is_running() {
+            break;
+        }
+        let Ok(stream) = stream else {
+            continue;
+        };
+        // Второй рубеж после каталога 0700: права каталога могут изменить
+        // уже после запуска агента
+        if !is_same_user(&stream) {
+            continue;
+        }
+
+        let id = shared.next_client.fetch_add(1, Ordering::SeqCst);
+        if let Ok(clone) = stream.try_clone() {
+            shared.lock_clients().insert(id, clone);
+        }
+
+        let client_shared = Arc::clone(&shared);
+        let spawned = std::thread::Builder::new()
+            .name("ssh-agent-client".into())
+            .spawn(move || {
+                serve_client(stream, &client_shared);
+                client_shared.lock_clients().remove(&id);
+            });
+        if spawned.is_err() {
+            shared.lock_clients().remove(&id);
+        }
+    }
+}
+
+fn serve_client(mut stream: UnixStream, shared: &Shared) {
+    while shared.is_running() {
+        let Ok(request) = read_message(&mut stream) else {
+            return;
+        };
+        let response = handle_request(&request, shared).unwrap_or_else(|e| {
+            eprintln!("SSH agent request failed: {e:#}");
+            vec![SSH_AGENT_FAILURE]
+        });
+        if write_message(&mut stream, &response).is_err() {
+            return;
+        }
+    }
+}
+
+fn handle_request(request: &[u8], shared: &Shared) -> Result<Vec<u8>> {
+    let mut reader = MessageReader(request);
+    match reader.byte()? {
+        SSH_AGENTC_REQUEST_IDENTITIES => list_identities(shared),
+        SSH_AGENTC_SIGN_REQUEST => {
+            let key_blob = reader.string()?;
+            let data = reader.string()?;
+            sign(shared, key_blob, data)
+        }
+        _ => Ok(vec![SSH_AGENT_FAILURE]),
+    }
+}
+
+fn list_identities(shared: &Shared) -> Result<Vec<u8>> {
+    let mut identities = Vec::new();
+    for entry in shared.keys()? {
+        let Some(key) = usable_key(&entry) else {
+            continue;
+        };
+        let comment = if entry.details.comment.is_empty() {
+            &entry.title
+        } else {
+            &entry.details.comment
+        };
+        identities.push((key.public_key().to_bytes()?, comment.clone()));
+    }
+
+    let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+    write_u32(&mut response, identities.len())?;
+    for (key_blob, comment) in identities {
+        write_string(&mut response, &key_blob)?;
+        write_string(&mut response, comment.as_bytes())?;
+    }
+
+    Ok(response)
+}
+
+fn sign(shared: &Shared, key_blob: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let mut found = None;
+    for entry in shared.keys()? {
+        let Some(key) = usable_key(&entry) else {
+            continue;
+        };
+        if key.public_key().to_bytes()? == key_blob {
+            found = Some((entry, key));
+            break;
+        }
+    }
+    let Some((entry, key)) = found else {
+        return Ok(vec![SSH_AGENT_FAILURE]);
+    };
+
+    // Хранилище не заблокировано, пока пользователь думает: подтверждение
+    // спрашивается без блокировки, а после ответа агент мог быть уже остановлен
+    if let Some(confirm) = &shared.confirm {
+        let key_use = KeyUse {
+            title: entry.title,
+            fingerprint: entry.details.fingerprint,
+        };
+        if !confirm(&key_use) {
+            return Ok(vec![SSH_AGENT_FAILURE]);
+        }
+    }
+    if !shared.is_running() {
+        return Ok(vec![SSH_AGENT_FAILURE]);
+    }
+
+    let signature = key.try_sign(data).context("Failed to sign with SSH key")?;
+
+    let mut blob = Vec::new();
+    write_string(&mut blob, signature.algorithm().as_str().as_bytes())?;
+    write_string(&mut blob, signature.as_bytes())?;
+
+    let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+    write_string(&mut response, &blob)?;
+    Ok(response)
+}
+
+/// Подключился ли клиент от имени того же пользователя, что и агент
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_same_user(stream: &UnixStream) -> bool {
+    use std::os::fd::AsRawFd;
+
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: передаём живой сокет и буфер размера `len` под структуру ucred
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut credentials as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+
+    result == 0 && credentials.uid == utils::fs::current_uid()
+}
+
+/// Подключился ли клиент от имени того же пользователя, что и агент
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn is_same_user(stream: &UnixStream) -> bool {
+    use std::os::fd::AsRawFd;
+
+    let mut uid = 0;
+    let mut gid = 0;
+    // SAFETY: передаём живой сокет и указатели на локальные переменные
+    let result = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) };
+
+    result == 0 && uid == utils::fs::current_uid()
+}
+
+/// Закрытый ключ записи, если агент может им подписывать
+fn usable_key(entry: &operations::SshKey) -> Option<PrivateKey> {
+    let key = utils::ssh_key::parse_private_key(&entry.details.private_key).ok()?;
+    (key.algorithm() == Algorithm::Ed25519 && !key.is_encrypted()).then_some(key)
+}
+
+fn read_message(stream: &mut UnixStream) -> Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 || len > MAX_MESSAGE_LEN {
+        anyhow::bail!("Invalid SSH agent message length: {len}");
+    }
+
+    let mut message = vec![0; len];
+    stream.read_exact(&mut message)?;
+    Ok(message)
+}
+
+fn write_message(stream: &mut UnixStream, message: &[u8]) -> Result<()> {
+    let mut framed = Vec::with_capacity(message.len() + 4);
+    write_string(&mut framed, message)?;
+    stream.write_all(&framed)?;
+    Ok(())
+}
+
+fn write_u32(buffer: &mut Vec<u8>, value: usize) -> Result<()> {
+    let value = u32::try_from(value).context("SSH agent message is too large")?;
+    buffer.extend_from_slice(&value.to_be_bytes());
+    Ok(())
+}
+
+/// Строка протокола SSH: длина и байты
+fn write_string(buffer: &mut Vec<u8>, value: &[u8]) -> Result<()> {
+    write_u32(buffer, value.len())?;
+    buffer.extend_from_slice(value);
+    Ok(())
+}
+
+struct MessageReader<'a>(&'a [u8]);
+
+impl<'a> MessageReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            anyhow::bail!("Truncated SSH agent message");
+        }
+        let (value, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(value)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn string(&mut self) -> Result<&'a [u8]> {
+        let len = self.take(4)?;
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::connection;
+    use signature::Verifier;
+
+    /// Открытое хранилище в памяти с ключом Ed25519 «deploy» и записью,
+    /// в которой вместо ключа мусор
+    fn unlocked_vault() -> Arc<Mutex<VaultInner>> {
+        let conn = connection::open_in_memory().unwrap();
+        let key = utils::ssh_key::generate_ed25519("").unwrap();
+        operations::add_ssh_key(&conn, "deploy", &key).unwrap();
+        let broken = operations::SshKeyDetails {
+            private_key: "not a key".to_string(),
+            ..Default::default()
+        };
+        operations::add_ssh_key(&conn, "broken", &broken).unwrap();
+
+        Arc::new(Mutex::new(VaultInner {
+            path: PathBuf::from(":memory:"),
+            connection: Some(conn),
+            is_locked: false,
+            backups: None,
+            agent: None,
+        }))
+    }
+
+    fn shared(vault: &Arc<Mutex<VaultInner>>, confirm: Option<Confirm>) -> Shared {
+        Shared {
+            running: AtomicBool::new(true),
+            vault: Arc::downgrade(vault),
+            confirm,
+            clients: Mutex::new(HashMap::new()),
+            next_client: AtomicU64::new(0),
+        }
+    }
+
+    fn public_key(vault: &Arc<Mutex<VaultInner>>) -> ssh_key::PublicKey {
+        let inner = vault.lock().unwrap();
+        let keys = operations::list_ssh_keys(inner.connection.as_ref().unwrap()).unwrap();
+        usable_key(&keys[0]).unwrap().public_key().clone()
+    }
+
+    fn sign_request(key_blob: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        write_string(&mut request, key_blob).unwrap();
+        write_string(&mut request, data).unwrap();
+        // Флаги подписи
+        write_u32(&mut request, 0).unwrap();
+        request
+    }
+
+    #[test]
+    fn reader_splits_protocol_strings() {
+        let mut message = vec![SSH_AGENTC_SIGN_REQUEST];
+        write_string(&mut message, b"key").unwrap();
+        write_string(&mut message, b"").unwrap();
+
+        let mut reader = MessageReader(&message);
+        assert_eq!(reader.byte().unwrap(), SSH_AGENTC_SIGN_REQUEST);
+        assert_eq!(reader.string().unwrap(), b"key");
+        assert_eq!(reader.string().unwrap(), b"");
+        assert!(reader.byte().is_err());
+    }
+
+    #[test]
+    fn reader_rejects_truncated_strings() {
+        let mut reader = MessageReader(&[0, 0, 0, 5, b'a', b'b']);
+        assert!(reader.string().is_err());
+
+        let mut reader = MessageReader(&[0, 0]);
+        assert!(reader.string().is_err());
+    }
+
+    #[test]
+    fn messages_are_length_prefixed() {
+        let (mut client, mut agent) = UnixStream::pair().unwrap();
+
+        write_message(&mut client, &[SSH_AGENTC_REQUEST_IDENTITIES]).unwrap();
+        assert_eq!(
+            read_message(&mut agent).unwrap(),
+            vec![SSH_AGENTC_REQUEST_IDENTITIES]
+        );
+    }
+
+    #[test]
+    fn empty_and_oversized_messages_are_rejected() {
+        let (mut client, mut agent) = UnixStream::pair().unwrap();
+
+        client.write_all(&0u32.to_be_bytes()).unwrap();
+        assert!(read_message(&mut agent).is_err());
+
+        let oversized = (MAX_MESSAGE_LEN as u32 + 1).to_be_bytes();
+        client.write_all(&oversized).unwrap();
+        assert!(read_message(&mut agent).is_err());
+    }
+
+    #[test]
+    fn identities_list_only_usable_keys() {
+        let vault = unlocked_vault();
+        let shared = shared(&vault, None);
+
+        let response = handle_request(&[SSH_AGENTC_REQUEST_IDENTITIES], &shared).unwrap();
+
+        let mut reader = MessageReader(&response);
+        assert_eq!(reader.byte().unwrap(), SSH_AGENT_IDENTITIES_ANSWER);
+        assert_eq!(reader.take(4).unwrap(), 1u32.to_be_bytes());
+        assert_eq!(
+            reader.string().unwrap(),
+            public_key(&vault).to_bytes().unwrap()
+        );
+        // Без комментария ключа показывается название записи
+        assert_eq!(reader.string().unwrap(), b"deploy");
+        assert!(reader.0.is_empty());
+    }
+
+    #[test]
+    fn locked_vault_has_no_identities() {
+        let vault = unlocked_vault();
+        vault.lock().unwrap().connection = None;
+
+        let response =
+            handle_request(&[SSH_AGENTC_REQUEST_IDENTITIES], &shared(&vault, None)).unwrap();
+
+        assert_eq!(response, [SSH_AGENT_IDENTITIES_ANSWER, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn signature_verifies_with_public_key() {
+        let vault = unlocked_vault();
+        let key = public_key(&vault);
+        let request = sign_request(&key.to_bytes().unwrap(), b"session data");
+
+        let response = handle_request(&request, &shared(&vault, None)).unwrap();
+
+        let mut reader = MessageReader(&response);
+        assert_eq!(reader.byte().unwrap(), SSH_AGENT_SIGN_RESPONSE);
+        let mut blob = MessageReader(reader.string().unwrap());
+        assert_eq!(blob.string().unwrap(), b"ssh-ed25519");
+        let signature =
+            ssh_key::Signature::new(Algorithm::Ed25519, blob.string().unwrap()).unwrap();
+        assert!(key.key_data().verify(b"session data", &signature).is_ok());
+    }
+
+    #[test]
+    fn declined_and_unknown_keys_are_not_used() {
+        let vault = unlocked_vault();
+        let key_blob = public_key(&vault).to_bytes().unwrap();
+        let confirm: Confirm = Arc::new(|key_use| {
+            assert_eq!(key_use.title, "deploy");
+            false
+        });
+
+        let declined = handle_request(
+            &sign_request(&key_blob, b"data"),
+            &shared(&vault, Some(confirm)),
+        );
+        assert_eq!(declined.unwrap(), [SSH_AGENT_FAILURE]);
+
+        let unknown = handle_request(&sign_request(b"other key", b"data"), &shared(&vault, None));
+        assert_eq!(unknown.unwrap(), [SSH_AGENT_FAILURE]);
+    }
+
+    #[test]
+    fn unsupported_requests_fail() {
+        let vault = unlocked_vault();
+        let shared = shared(&vault, None);
+
+        assert_eq!(handle_request(&[27], &shared).unwrap(), [SSH_AGENT_FAILURE]);
+        assert!(handle_request(&[], &shared).is_err());
+        assert!(handle_request(&[SSH_AGENTC_SIGN_REQUEST, 0, 0], &shared).is_err());
+    }
+}
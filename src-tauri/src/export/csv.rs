@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use super::TYPED_FIELDS;
+use crate::db::operations::EntryRecord;
+use crate::utils::fs::create_private_file;
+
+/// Общие столбцы CSV-экспорта; за ними идут столбцы карт, личных данных и ключей SSH
+const HEADER: [&str; 7] = [
+    "type", "site", "login", "password", "url", "notes", "folder",
+];
+
+/// Записывает записи в незашифрованный CSV-файл с правами 0600
+pub fn write_csv<P: AsRef<Path>>(path: P, records: &[EntryRecord]) -> Result<()> {
+    let file = create_private_file(path)?;
+    let mut writer = ::csv::Writer::from_writer(file);
+
+    let header = HEADER
+        .into_iter()
+        .chain(TYPED_FIELDS.iter().map(|field| field.column));
+    writer
+        .write_record(header)
+        .context("Failed to write CSV header")?;
+
+    for record in records {
+        writer
+            .write_record(row(record))
+            .context("Failed to write CSV row")?;
+    }
+
+    writer.flush().context("Failed to flush CSV file")?;
+
+    Ok(())
+}
+
+/// Строка CSV в порядке заголовка; поля чужого типа записи остаются пустыми
+fn row(record: &EntryRecord) -> Vec<String> {
+    let common = [
+        record.entry_type.as_str(),
+        record.site.as_str(),
+        record.login.as_str(),
+        record.password.as_str(),
+        record.url.as_deref().unwrap_or_default(),
+        record.notes.as_deref().unwrap_or_default(),
+        record.folder.as_deref().unwrap_or_default(),
+    ];
+
+    common
+        .into_iter()
+        .map(str::to_string)
+        .chain(
+            TYPED_FIELDS
+                .iter()
+                .map(|field| field.get(record).unwrap_or_default()),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::operations::{CardDetails, EntryType};
+    use crate::utils::fs::TestDir;
+
+    fn columns() -> Vec<&'static str> {
+        HEADER
+            .into_iter()
+            .chain(TYPED_FIELDS.iter().map(|field| field.column))
+            .collect()
+    }
+
+    #[test]
+    fn card_fields_are_written_to_their_columns() {
+        let record = EntryRecord {
+            entry_type: EntryType::Card,
+            site: "Visa".to_string(),
+            card: Some(CardDetails {
+                holder: "IVAN PETROV".to_string(),
+                number: "4111111111111111".to_string(),
+                expiry_month: Some(12),
+                expiry_year: Some(2030),
+                cvv: Some("123".to_string()),
+                pin: None,
+            }),
+            ..Default::default()
+        };
+
+        let header = columns();
+        let row = row(&record);
+        assert_eq!(row.len(), header.len());
+
+        let column = |name: &str| {
+            let position = header.iter().position(|column| *column == name).unwrap();
+            row[position].as_str()
+        };
+        assert_eq!(column("type"), "card");
+        assert_eq!(column("site"), "Visa");
+        assert_eq!(column("card_number"), "4111111111111111");
+        assert_eq!(column("card_expiry_month"), "12");
+        assert_eq!(column("card_expiry_year"), "2030");
+        assert_eq!(column("card_cvv"), "123");
+        assert_eq!(column("card_pin"), "");
+        assert_eq!(column("full_name"), "");
+    }
+
+    #[test]
+    fn records_are_written_under_the_header() {
+        let dir = TestDir::new("csv-export");
+        let path = dir.join("export.csv");
+        let records = [EntryRecord {
+            site: "GitHub".to_string(),
+            login: "octocat".to_string(),
+            password: "pa,ss\"word".to_string(),
+            url: Some("https://github.com".to_string()),
+            notes: Some("строка 1\nстрока 2".to_string()),
+            ..Default::default()
+        }];
+
+        write_csv(&path, &records).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let (header, row) = contents.split_once('\n').unwrap();
+        assert_eq!(header, columns().join(","));
+        // Поля карт, личных данных и ключей SSH у логина пустые
+        let empty = ",".repeat(TYPED_FIELDS.len());
+        assert_eq!(
+            row,
+            format!(
+                "login,GitHub,octocat,\"pa,ss\"\"word\",https://github.com,\
+                 \"строка 1\nстрока 2\",{empty}\n"
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn existing_file_is_truncated_and_made_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TestDir::new("csv-existing");
+        let path = dir.join("export.csv");
+        std::fs::write(&path, "old contents that are longer than the new export").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_csv(&path, &[]).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", columns().join(","))
+        );
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
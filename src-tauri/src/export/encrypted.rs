@@ -0,0 +1,328 @@
+//! Зашифрованный JSON-экспорт хранилища.
+//!
+//! Файл экспорта — JSON-объект версии 1:
+//!
+//! ```json
+//! {
+//!   "format": "nopeekpanda-export",
+//!   "version": 1,
+//!   "kdf": { "algorithm": "argon2id", "memory_kib": 65536, "iterations": 3,
+//!            "parallelism": 4, "salt": "<base64, 16 байт>" },
+//!   "cipher": { "algorithm": "xchacha20-poly1305", "nonce": "<base64, 24 байта>" },
+//!   "payload": "<base64 шифротекста>"
+//! }
+//! ```
+//!
+//! Ключ (32 байта) выводится из пароля экспорта через Argon2id с параметрами
+//! из `kdf`. `payload` — XChaCha20-Poly1305 от UTF-8 JSON вида
+//! `{ "vault_name", "app_version", "exported_at", "entries": [EntryRecord] }`;
+//! строка `"nopeekpanda-export:v1"` передаётся как связанные данные (AAD),
+//! так что подмена версии формата ломает расшифровку.
+
+use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use secrecy::{ExposeSecret, SecretBox as Secret};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::db::operations::{self, EntryRecord};
+use crate::utils::memory;
+
+const FORMAT_NAME: &str = "nopeekpanda-export";
+const FORMAT_VERSION: u32 = 1;
+const AAD: &[u8] = b"nopeekpanda-export:v1";
+
+const KDF_ALGORITHM: &str = "argon2id";
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 4;
+const SALT_LEN: usize = 16;
+
+/// Пределы параметров Argon2id при расшифровке: параметры приходят из файла,
+/// и без пределов чужой файл мог бы заставить выделить гигабайты памяти
+const KDF_MEMORY_KIB_RANGE: std::ops::RangeInclusive<u32> = 8 * 1024..=256 * 1024;
+const KDF_ITERATIONS_RANGE: std::ops::RangeInclusive<u32> = 1..=10;
+const KDF_PARALLELISM_RANGE: std::ops::RangeInclusive<u32> = 1..=8;
+
+const CIPHER_ALGORITHM: &str = "xchacha20-poly1305";
+const NONCE_LEN: usize = 24;
+
+/// Внешняя (незашифрованная) оболочка файла экспорта
+#[derive(Serialize, Deserialize)]
+struct ExportFile {
+    format: String,
+    version: u32,
+    kdf: KdfParams,
+    cipher: CipherParams,
+    payload: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KdfParams {
+    algorithm: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CipherParams {
+    algorithm: String,
+    nonce: String,
+}
+
+/// Расшифрованное содержимое экспорта
+#[derive(Serialize, Deserialize)]
+pub struct ExportPayload {
+    pub vault_name: String,
+    pub app_version: String,
+    /// Время экспорта, секунды Unix
+    pub exported_at: i64,
+    pub entries: Vec<EntryRecord>,
+}
+
+impl ExportPayload {
+    pub fn new(vault_name: String, entries: Vec<EntryRecord>) -> Self {
+        Self {
+            vault_name,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: operations::unix_now(),
+            entries,
+        }
+    }
+}
+
+/// Шифрует содержимое экспорта паролем и возвращает готовый JSON-файл
+pub fn encrypt(payload: &ExportPayload, export_password: &Secret<String>) -> Result<String> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rng().fill_bytes(&mut salt);
+    rand::rng().fill_bytes(&mut nonce);
+
+    let kdf = KdfParams {
+        algorithm: KDF_ALGORITHM.to_string(),
+        memory_kib: KDF_MEMORY_KIB,
+        iterations: KDF_ITERATIONS,
+        parallelism: KDF_PARALLELISM,
+        salt: BASE64.encode(salt),
+    };
+    let key = derive_key(export_password, &kdf, &salt)?;
+
+    let plaintext = Zeroizing::new(
+        serde_json::to_vec(payload).context("Failed to serialize export payload")?,
+    );
+    let cipher = XChaCha20Poly1305::new_from_slice(key.as_ref())
+        .map_err(|_| anyhow::anyhow!("Invalid export key length"))?;
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: AAD,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt export payload"))?;
+
+    let file = ExportFile {
+        format: FORMAT_NAME.to_string(),
+        version: FORMAT_VERSION,
+        kdf,
+        cipher: CipherParams {
+            algorithm: CIPHER_ALGORITHM.to_string(),
+            nonce: BASE64.encode(nonce),
+        },
+        payload: BASE64.encode(ciphertext),
+    };
+
+    serde_json::to_string_pretty(&file).context("Failed to serialize export file")
+}
+
+/// Проверяет заголовок файла экспорта и расшифровывает его содержимое
+pub fn decrypt(contents: &str, export_password: &Secret<String>) -> Result<ExportPayload> {
+    let file: ExportFile =
+        serde_json::from_str(contents).context("File is not a NoPeekPanda export")?;
+
+    if file.format != FORMAT_NAME {
+        anyhow::bail!("File is not a NoPeekPanda export");
+    }
+    if file.version != FORMAT_VERSION {
+        anyhow::bail!("Unsupported export version: {}", file.version);
+    }
+    if file.kdf.algorithm != KDF_ALGORITHM || file.cipher.algorithm != CIPHER_ALGORITHM {
+        anyhow::bail!("Unsupported export algorithms");
+    }
+    check_kdf_params(&file.kdf)?;
+
+    let salt = BASE64
+        .decode(&file.kdf.salt)
+        .context("Invalid export salt")?;
+    if salt.len() != SALT_LEN {
+        anyhow::bail!("Invalid export salt length");
+    }
+    let nonce = BASE64
+        .decode(&file.cipher.nonce)
+        .context("Invalid export nonce")?;
+    if nonce.len() != NONCE_LEN {
+        anyhow::bail!("Invalid export nonce length");
+    }
+    let ciphertext = BASE64
+        .decode(&file.payload)
+        .context("Invalid export payload")?;
+
+    let key = derive_key(export_password, &file.kdf, &salt)?;
+    let cipher = XChaCha20Poly1305::new_from_slice(key.as_ref())
+        .map_err(|_| anyhow::anyhow!("Invalid export key length"))?;
+    let plaintext = Zeroizing::new(
+        cipher
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: AAD,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Wrong export password or corrupted file"))?,
+    );
+
+    serde_json::from_slice(&plaintext).context("Invalid export payload")
+}
+
+/// Отклоняет параметры Argon2id за пределами `KDF_*_RANGE`
+fn check_kdf_params(kdf: &KdfParams) -> Result<()> {
+    if !KDF_MEMORY_KIB_RANGE.contains(&kdf.memory_kib)
+        || !KDF_ITERATIONS_RANGE.contains(&kdf.iterations)
+        || !KDF_PARALLELISM_RANGE.contains(&kdf.parallelism)
+    {
+        anyhow::bail!(
+            "Unsupported KDF parameters: memory {} KiB, {} iterations, parallelism {}",
+            kdf.memory_kib,
+            kdf.iterations,
+            kdf.parallelism
+        );
+    }
+
+    Ok(())
+}
+
+/// Выводит 32-байтный ключ из пароля экспорта через Argon2id
+fn derive_key(
+    export_password: &Secret<String>,
+    kdf: &KdfParams,
+    salt: &[u8],
+) -> Result<Zeroizing<[u8; 32]>> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| anyhow::anyhow!("Invalid KDF parameters: {}", e))?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+    let password = memory::locked_copy(export_password.expose_secret().as_bytes());
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    argon2
+        .hash_password_into(&password, salt, key.as_mut())
+        .map_err(|e| anyhow::anyhow!("Failed to derive export key: {}", e))?;
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::operations::{AttachmentRecord, CardDetails, EntryType, PasswordHistoryItem};
+
+    fn password(value: &str) -> Secret<String> {
+        Secret::new(Box::new(value.to_string()))
+    }
+
+    fn sample_payload() -> ExportPayload {
+        ExportPayload::new(
+            "Personal".to_string(),
+            vec![
+                EntryRecord {
+                    site: "GitHub".to_string(),
+                    login: "octocat".to_string(),
+                    password: "current".to_string(),
+                    folder: Some("Work/Dev".to_string()),
+                    history: vec![PasswordHistoryItem {
+                        password: "first".to_string(),
+                        changed_at: 1_650_000_000,
+                    }],
+                    attachments: vec![AttachmentRecord {
+                        name: "key.bin".to_string(),
+                        data: vec![0, 1, 2, 255],
+                        created_at: 1_640_000_000,
+                    }],
+                    created_at: 1_640_000_000,
+                    updated_at: 1_670_000_000,
+                    ..Default::default()
+                },
+                EntryRecord {
+                    entry_type: EntryType::Card,
+                    site: "Visa".to_string(),
+                    card: Some(CardDetails {
+                        holder: "IVAN PETROV".to_string(),
+                        number: "4111111111111111".to_string(),
+                        expiry_month: Some(12),
+                        expiry_year: Some(2030),
+                        cvv: Some("123".to_string()),
+                        pin: None,
+                    }),
+                    ..Default::default()
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn encrypt_then_decrypt_is_lossless() {
+        let payload = sample_payload();
+
+        let file = encrypt(&payload, &password("export")).unwrap();
+        let decrypted = decrypt(&file, &password("export")).unwrap();
+
+        assert_eq!(decrypted.vault_name, payload.vault_name);
+        assert_eq!(decrypted.app_version, payload.app_version);
+        assert_eq!(decrypted.exported_at, payload.exported_at);
+        assert_eq!(decrypted.entries, payload.entries);
+        assert!(decrypt(&file, &password("wrong")).is_err());
+    }
+
+    #[test]
+    fn oversized_kdf_parameters_are_rejected() {
+        // Параметры проверяются до вывода ключа, поэтому шифротекст может быть любым
+        let file = serde_json::json!({
+            "format": FORMAT_NAME,
+            "version": FORMAT_VERSION,
+            "kdf": {
+                "algorithm": KDF_ALGORITHM,
+                "memory_kib": KDF_MEMORY_KIB,
+                "iterations": KDF_ITERATIONS,
+                "parallelism": KDF_PARALLELISM,
+                "salt": BASE64.encode([0u8; SALT_LEN]),
+            },
+            "cipher": {
+                "algorithm": CIPHER_ALGORITHM,
+                "nonce": BASE64.encode([0u8; NONCE_LEN]),
+            },
+            "payload": "",
+        });
+
+        for (field, value) in [
+            ("memory_kib", 4 * 1024 * 1024),
+            ("iterations", 1_000),
+            ("parallelism", 255),
+        ] {
+            let mut json = file.clone();
+            json["kdf"][field] = value.into();
+
+            let error = decrypt(&json.to_string(), &password("export"))
+                .err()
+                .unwrap();
+            assert!(error.to_string().contains("Unsupported KDF parameters"));
+        }
+    }
+}
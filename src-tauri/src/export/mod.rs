@@ -0,0 +1,210 @@
+pub mod csv;
+pub mod encrypted;
+pub mod kdbx;
+
+use crate::db::operations::{EntryRecord, EntryType};
+
+/// Поле карты, личных данных или ключа SSH для форматов, где таких типов
+/// записей нет: в CSV оно становится столбцом, в KDBX — полем записи
+pub struct TypedField {
+    pub entry_type: EntryType,
+    /// Имя столбца CSV
+    pub column: &'static str,
+    /// Имя поля KDBX
+    pub label: &'static str,
+    /// Секретное значение: в KDBX пишется защищённой строкой
+    pub protected: bool,
+    get: fn(&EntryRecord) -> Option<String>,
+    set: fn(&mut EntryRecord, String),
+}
+
+impl TypedField {
+    /// Значение поля записи; пустое значение — `None`
+    pub fn get(&self, record: &EntryRecord) -> Option<String> {
+        if record.entry_type != self.entry_type {
+            return None;
+        }
+        (self.get)(record).filter(|value| !value.is_empty())
+    }
+
+    /// Записывает значение поля в данные карты, личные данные или ключ
+    pub fn set(&self, record: &mut EntryRecord, value: String) {
+        (self.set)(record, value)
+    }
+}
+
+/// Поля записей, которые не помещаются в логин, пароль, адрес и заметки
+pub const TYPED_FIELDS: [TypedField; 15] = [
+    TypedField {
+        entry_type: EntryType::Card,
+        column: "card_holder",
+        label: "Card holder",
+        protected: false,
+        get: |record| record.card.as_ref().map(|card| card.holder.clone()),
+        set: |record, value| record.card.get_or_insert_with(Default::default).holder = value,
+    },
+    TypedField {
+        entry_type: EntryType::Card,
+        column: "card_number",
+        label: "Card number",
+        protected: true,
+        get: |record| record.card.as_ref().map(|card| card.number.clone()),
+        set: |record, value| record.card.get_or_insert_with(Default::default).number = value,
+    },
+    TypedField {
+        entry_type: EntryType::Card,
+        column: "card_expiry_month",
+        label: "Expiry month",
+        protected: false,
+        get: |record| {
+            let month = record.card.as_ref()?.expiry_month?;
+            Some(month.to_string())
+        },
+        set: |record, value| {
+            record
+                .card
+                .get_or_insert_with(Default::default)
+                .expiry_month = value.parse().ok()
+        },
+    },
+    TypedField {
+        entry_type: EntryType::Card,
+        column: "card_expiry_year",
+        label: "Expiry year",
+        protected: false,
+        get: |record| {
+            let year = record.card.as_ref()?.expiry_year?;
+            Some(year.to_string())
+        },
+        set: |record, value| {
+            record.card.get_or_insert_with(Default::default).expiry_year = value.parse().ok()
+        },
+    },
+    TypedField {
+        entry_type: EntryType::Card,
+        column: "card_cvv",
+        label: "CVV",
+        protected: true,
+        get: |record| record.card.as_ref()?.cvv.clone(),
+        set: |record, value| record.card.get_or_insert_with(Default::default).cvv = Some(value),
+    },
+    TypedField {
+        entry_type: EntryType::Card,
+        column: "card_pin",
+        label: "PIN",
+        protected: true,
+        get: |record| record.card.as_ref()?.pin.clone(),
+        set: |record, value| record.card.get_or_insert_with(Default::default).pin = Some(value),
+    },
+    TypedField {
+        entry_type: EntryType::Identity,
+        column: "full_name",
+        label: "Full name",
+        protected: false,
+        get: |record| {
+            record
+                .identity
+                .as_ref()
+                .map(|identity| identity.full_name.clone())
+        },
+        set: |record, value| {
+            record
+                .identity
+                .get_or_insert_with(Default::default)
+                .full_name = value
+        },
+    },
+    TypedField {
+        entry_type: EntryType::Identity,
+        column: "address",
+        label: "Address",
+        protected: false,
+        get: |record| record.identity.as_ref()?.address.clone(),
+        set: |record, value| {
+            record.identity.get_or_insert_with(Default::default).address = Some(value)
+        },
+    },
+    TypedField {
+        entry_type: EntryType::Identity,
+        column: "phone",
+        label: "Phone",
+        protected: false,
+        get: |record| record.identity.as_ref()?.phone.clone(),
+        set: |record, value| {
+            record.identity.get_or_insert_with(Default::default).phone = Some(value)
+        },
+    },
+    TypedField {
+        entry_type: EntryType::Identity,
+        column: "passport_number",
+        label: "Passport number",
+        protected: true,
+        get: |record| record.identity.as_ref()?.passport_number.clone(),
+        set: |record, value| {
+            record
+                .identity
+                .get_or_insert_with(Default::default)
+                .passport_number = Some(value)
+        },
+    },
+    TypedField {
+        entry_type: EntryType::Identity,
+        column: "id_number",
+        label: "ID number",
+        protected: true,
+        get: |record| record.identity.as_ref()?.id_number.clone(),
+        set: |record, value| {
+            record
+                .identity
+                .get_or_insert_with(Default::default)
+                .id_number = Some(value)
+        },
+    },
+    TypedField {
+        entry_type: EntryType::SshKey,
+        column: "ssh_private_key",
+        label: "Private key",
+        protected: true,
+        get: |record| record.ssh_key.as_ref().map(|key| key.private_key.clone()),
+        set: |record, value| {
+            record
+                .ssh_key
+                .get_or_insert_with(Default::default)
+                .private_key = value
+        },
+    },
+    TypedField {
+        entry_type: EntryType::SshKey,
+        column: "ssh_public_key",
+        label: "Public key",
+        protected: false,
+        get: |record| record.ssh_key.as_ref().map(|key| key.public_key.clone()),
+        set: |record, value| {
+            record
+                .ssh_key
+                .get_or_insert_with(Default::default)
+                .public_key = value
+        },
+    },
+    TypedField {
+        entry_type: EntryType::SshKey,
+        column: "ssh_fingerprint",
+        label: "Fingerprint",
+        protected: false,
+        get: |record| record.ssh_key.as_ref().map(|key| key.fingerprint.clone()),
+        set: |record, value| {
+            record
+                .ssh_key
+                .get_or_insert_with(Default::default)
+                .fingerprint = value
+        },
+    },
+    TypedField {
+        entry_type: EntryType::SshKey,
+        column: "ssh_comment",
+        label: "Key comment",
+        protected: false,
+        get: |record| record.ssh_key.as_ref().map(|key| key.comment.clone()),
+        set: |record, value| record.ssh_key.get_or_insert_with(Default::default).comment = value,
+    },
+];
@@ -0,0 +1,227 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::{non_empty, site_name, ParsedImport, SkippedItem};
+use crate::db::operations::{CustomField, EntryRecord};
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ChromiumRow {
+    name: String,
+    url: String,
+    username: String,
+    password: String,
+    note: String,
+}
+
+/// Разбирает CSV-экспорт паролей Chrome, Edge и других браузеров на Chromium
+/// (`name,url,username,password,note`)
+pub fn parse_chromium_csv(contents: &str) -> Result<ParsedImport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let headers = reader.headers().context("Failed to read CSV header")?;
+    if !["name", "url", "username", "password"]
+        .iter()
+        .all(|column| headers.iter().any(|h| h == *column))
+    {
+        anyhow::bail!("File is not a Chromium password export");
+    }
+
+    let mut parsed = ParsedImport::default();
+    for (line, row) in reader.deserialize::<ChromiumRow>().enumerate() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                parsed.skipped.push(invalid_row(line, e));
+                continue;
+            }
+        };
+
+        let url = non_empty(Some(row.url));
+        // Chrome пишет в `name` хост сайта, но для Android-приложений оно бывает пустым
+        let site = non_empty(Some(row.name))
+            .or_else(|| url.as_deref().and_then(site_name))
+            .unwrap_or_default();
+
+        parsed.records.push(EntryRecord {
+            site,
+            login: row.username,
+            password: row.password,
+            url,
+            notes: non_empty(Some(row.note)),
+            ..Default::default()
+        });
+    }
+
+    Ok(parsed)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct FirefoxRow {
+    url: String,
+    username: String,
+    password: String,
+    http_realm: String,
+    time_created: String,
+    time_password_changed: String,
+}
+
+/// Разбирает CSV-экспорт паролей Firefox
+/// (`url,username,password,httpRealm,formActionOrigin,guid,timeCreated,timeLastUsed,timePasswordChanged`)
+pub fn parse_firefox_csv(contents: &str) -> Result<ParsedImport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let headers = reader.headers().context("Failed to read CSV header")?;
+    if !headers.iter().any(|h| h == "httpRealm") {
+        anyhow::bail!("File is not a Firefox password export");
+    }
+
+    let mut parsed = ParsedImport::default();
+    for (line, row) in reader.deserialize::<FirefoxRow>().enumerate() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                parsed.skipped.push(invalid_row(line, e));
+                continue;
+            }
+        };
+
+        // Служебные записи самого браузера (chrome://FirefoxAccounts) не переносим
+        if row.url.starts_with("chrome://") {
+            parsed.skipped.push(SkippedItem {
+                name: row.url,
+                reason: "Browser-internal login".to_string(),
+            });
+            continue;
+        }
+
+        let mut custom_fields = Vec::new();
+        if let Some(realm) = non_empty(Some(row.http_realm)) {
+            custom_fields.push(CustomField {
+                name: "HTTP realm".to_string(),
+                value: realm,
+                hidden: false,
+            });
+        }
+
+        let created_at = millis_to_secs(&row.time_created).unwrap_or(0);
+        let updated_at = millis_to_secs(&row.time_password_changed).unwrap_or(created_at);
+        let url = non_empty(Some(row.url));
+
+        parsed.records.push(EntryRecord {
+            site: url.as_deref().and_then(site_name).unwrap_or_default(),
+            login: row.username,
+            password: row.password,
+            url,
+            custom_fields,
+            created_at,
+            updated_at,
+            ..Default::default()
+        });
+    }
+
+    Ok(parsed)
+}
+
+/// Firefox хранит время в миллисекундах Unix
+fn millis_to_secs(value: &str) -> Option<i64> {
+    value.trim().parse::<i64>().ok().map(|millis| millis / 1000)
+}
+
+fn invalid_row(line: usize, error: csv::Error) -> SkippedItem {
+    SkippedItem {
+        name: format!("Row {}", line + 2),
+        reason: format!("Invalid CSV row: {error}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Экспорт Chrome: сайт, Android-приложение без имени и значения с кавычками
+    const CHROME_EXPORT: &str = include_str!("../../tests/fixtures/chrome_passwords.csv");
+    /// Экспорт Firefox: сайт, HTTP-авторизация без смены пароля и служебная запись
+    const FIREFOX_EXPORT: &str = include_str!("../../tests/fixtures/firefox_logins.csv");
+
+    #[test]
+    fn chromium_rows_become_logins() {
+        let parsed = parse_chromium_csv(CHROME_EXPORT).unwrap();
+
+        assert!(parsed.skipped.is_empty());
+        assert_eq!(parsed.records.len(), 3);
+
+        let github = &parsed.records[0];
+        assert_eq!(github.site, "github.com");
+        assert_eq!(github.login, "octocat");
+        assert_eq!(github.password, "current");
+        assert_eq!(github.url.as_deref(), Some("https://github.com/login"));
+        assert_eq!(github.notes.as_deref(), Some("Рабочий аккаунт"));
+
+        // У приложения Android имя пустое — берётся хост адреса
+        let app = &parsed.records[1];
+        assert_eq!(app.site, "com.example.app");
+        assert_eq!(app.notes, None);
+
+        let quoted = &parsed.records[2];
+        assert_eq!(quoted.site, "www.example.org");
+        assert_eq!(quoted.login, "quoted, user");
+        assert_eq!(quoted.password, "pa\"ss");
+    }
+
+    #[test]
+    fn chromium_parser_rejects_other_csv() {
+        let error = parse_chromium_csv(FIREFOX_EXPORT).err().unwrap();
+
+        assert_eq!(error.to_string(), "File is not a Chromium password export");
+    }
+
+    #[test]
+    fn firefox_rows_keep_realm_and_times() {
+        let parsed = parse_firefox_csv(FIREFOX_EXPORT).unwrap();
+
+        assert_eq!(parsed.records.len(), 2);
+
+        let github = &parsed.records[0];
+        assert_eq!(github.site, "github.com");
+        assert_eq!(github.login, "octocat");
+        assert_eq!(github.password, "current");
+        assert!(github.custom_fields.is_empty());
+        assert_eq!(github.created_at, 1_673_341_200);
+        assert_eq!(github.updated_at, 1_709_287_200);
+
+        // Без времени смены пароля запись считается не менявшейся с создания
+        let router = &parsed.records[1];
+        assert_eq!(router.site, "router.local");
+        assert_eq!(
+            router.custom_fields,
+            vec![CustomField {
+                name: "HTTP realm".to_string(),
+                value: "Router admin".to_string(),
+                hidden: false,
+            }]
+        );
+        assert_eq!(router.updated_at, router.created_at);
+    }
+
+    #[test]
+    fn firefox_internal_logins_are_skipped() {
+        let parsed = parse_firefox_csv(FIREFOX_EXPORT).unwrap();
+
+        assert_eq!(parsed.skipped.len(), 1);
+        assert_eq!(parsed.skipped[0].name, "chrome://FirefoxAccounts");
+        assert_eq!(parsed.skipped[0].reason, "Browser-internal login");
+    }
+
+    #[test]
+    fn firefox_parser_rejects_other_csv() {
+        let error = parse_firefox_csv(CHROME_EXPORT).err().unwrap();
+
+        assert_eq!(error.to_string(), "File is not a Firefox password export");
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use super::{site_name, ConflictResolution};
+use crate::db::operations::{EntryRecord, EntryType, PasswordHistoryItem};
+
+/// Ключ сравнения записей: тип записи, хост адреса без «www.»
+/// (или название, если адреса нет) и логин
+pub type MatchKey = (EntryType, String, String);
+
+/// Ключ сравнения записи. Сравниваются только логины: у заметок, карт и
+/// других записей нет логина и пароля, и совпадение по одному названию
+/// привело бы к замене или слиянию записей разных типов.
+pub fn match_key(record: &EntryRecord) -> Option<MatchKey> {
+    if record.entry_type != EntryType::Login {
+        return None;
+    }
+
+    let host = record
+        .url
+        .as_deref()
+        .and_then(site_name)
+        .or_else(|| site_name(&record.site))
+        .unwrap_or_else(|| record.site.trim().to_lowercase());
+
+    Some((record.entry_type, host, record.login.trim().to_lowercase()))
+}
+
+/// Индекс существующих записей по ключу сравнения; при повторах берётся первая
+pub fn index(existing: &[(u64, EntryRecord)]) -> HashMap<MatchKey, usize> {
+    let mut index = HashMap::new();
+    for (position, (_, record)) in existing.iter().enumerate() {
+        if let Some(key) = match_key(record) {
+            index.entry(key).or_insert(position);
+        }
+    }
+    index
+}
+
+/// Решение по умолчанию: точный повтор пропускается, иначе поля объединяются без потерь
+pub fn suggest(existing: &EntryRecord, incoming: &EntryRecord) -> ConflictResolution {
+    if existing.password == incoming.password {
+        ConflictResolution::Skip
+    } else {
+        ConflictResolution::Merge
+    }
+}
+
+/// Заменяет поля записи импортированными; прежний пароль уходит в историю.
+/// Вложения существующей записи остаются, если в импорте своих нет
+pub fn overwrite(existing: &EntryRecord, incoming: EntryRecord, now: i64) -> EntryRecord {
+    let mut history = existing.history.clone();
+    if existing.password != incoming.password {
+        history.push(PasswordHistoryItem {
+            password: existing.password.clone(),
+            changed_at: now,
+        });
+    }
+    history.extend(incoming.history.iter().cloned());
+    let attachments = if incoming.attachments.is_empty() {
+        existing.attachments.clone()
+    } else {
+        incoming.attachments
+    };
+
+    EntryRecord {
+        history: normalize_history(history),
+        attachments,
+        created_at: earliest(existing.created_at, incoming.created_at),
+        updated_at: now,
+        ..incoming
+    }
+}
+
+/// Объединяет записи: заполненные поля существующей записи сохраняются,
+/// недостающие берутся из импорта, более старый из двух паролей уходит в историю
+pub fn merge(existing: &EntryRecord, incoming: EntryRecord, now: i64) -> EntryRecord {
+    let mut merged = existing.clone();
+
+    merged.url = merged.url.or(incoming.url);
+    merged.folder = merged.folder.or(incoming.folder);
+    merged.totp = merged.totp.or(incoming.totp);
+    merged.notes = match (merged.notes, incoming.notes) {
+        (Some(own), Some(other)) if own != other => Some(format!("{own}\n\n{other}")),
+        (own, other) => own.or(other),
+    };
+
+    merged.history.extend(incoming.history);
+    if existing.password != incoming.password {
+        let (newer, older, replaced_at) = if incoming.updated_at > existing.updated_at {
+            (
+                incoming.password,
+                existing.password.clone(),
+                incoming.updated_at,
+            )
+        } else {
+            (
+                existing.password.clone(),
+                incoming.password,
+                existing.updated_at,
+            )
+        };
+        merged.password = newer;
+        merged.history.push(PasswordHistoryItem {
+            password: older,
+            changed_at: replaced_at,
+        });
+    }
+    merged.history = normalize_history(merged.history);
+
+    for field in incoming.custom_fields {
+        if !merged.custom_fields.contains(&field) {
+            merged.custom_fields.push(field);
+        }
+    }
+    for attachment in incoming.attachments {
+        let duplicate = merged
+            .attachments
+            .iter()
+            .any(|own| own.name == attachment.name && own.data == attachment.data);
+        if !duplicate {
+            merged.attachments.push(attachment);
+        }
+    }
+
+    merged.created_at = earliest(existing.created_at, incoming.created_at);
+    merged.updated_at = now;
+    merged
+}
+
+/// История от старых к новым без повторов
+fn normalize_history(mut history: Vec<PasswordHistoryItem>) -> Vec<PasswordHistoryItem> {
+    history.sort_by(|a, b| {
+        a.changed_at
+            .cmp(&b.changed_at)
+            .then_with(|| a.password.cmp(&b.password))
+    });
+    history.dedup();
+    history
+}
+
+/// Меньшая из двух меток времени; 0 означает «неизвестно»
+fn earliest(a: i64, b: i64) -> i64 {
+    match (a, b) {
+        (0, other) | (other, 0) => other,
+        (a, b) => a.min(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::operations::{AttachmentRecord, CustomField};
+
+    fn login(password: &str, updated_at: i64) -> EntryRecord {
+        EntryRecord {
+            site: "GitHub".to_string(),
+            login: "octocat".to_string(),
+            password: password.to_string(),
+            url: Some("https://www.github.com/login".to_string()),
+            created_at: 1_000,
+            updated_at,
+            ..Default::default()
+        }
+    }
+
+    fn history(record: &EntryRecord) -> Vec<(&str, i64)> {
+        record
+            .history
+            .iter()
+            .map(|item| (item.password.as_str(), item.changed_at))
+            .collect()
+    }
+
+    #[test]
+    fn match_key_ignores_case_and_www() {
+        let mut other = login("secret", 0);
+        other.site = "github".to_string();
+        other.login = " OctoCat ".to_string();
+        other.url = Some("https://github.com/".to_string());
+
+        assert_eq!(match_key(&login("secret", 0)), match_key(&other));
+        assert_eq!(
+            match_key(&other),
+            Some((
+                EntryType::Login,
+                "github.com".to_string(),
+                "octocat".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn match_key_without_url_uses_site() {
+        let mut record = login("secret", 0);
+        record.url = None;
+        record.site = " My Router ".to_string();
+
+        let (_, host, _) = match_key(&record).unwrap();
+        assert_eq!(host, "my router");
+    }
+
+    #[test]
+    fn only_logins_are_matched() {
+        let note = EntryRecord {
+            entry_type: EntryType::SecureNote,
+            site: "GitHub".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(match_key(&note), None);
+        let existing = vec![(1, note), (2, login("a", 0)), (3, login("b", 0))];
+        let index = index(&existing);
+        assert_eq!(index.len(), 1);
+        // При повторах берётся первая запись
+        assert_eq!(index.values().copied().collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn suggest_skips_exact_duplicates() {
+        assert_eq!(
+            suggest(&login("same", 10), &login("same", 20)),
+            ConflictResolution::Skip
+        );
+        assert_eq!(
+            suggest(&login("old", 10), &login("new", 20)),
+            ConflictResolution::Merge
+        );
+    }
+
+    #[test]
+    fn merge_keeps_newer_password_and_existing_fields() {
+        let mut existing = login("old", 2_000);
+        existing.notes = Some("своя заметка".to_string());
+        existing.custom_fields.push(CustomField {
+            name: "PIN".to_string(),
+            value: "1234".to_string(),
+            hidden: true,
+        });
+        existing.attachments.push(AttachmentRecord {
+            name: "key.txt".to_string(),
+            data: b"key".to_vec(),
+            created_at: 1_000,
+        });
+
+        let mut incoming = login("new", 3_000);
+        incoming.url = Some("https://github.com/other".to_string());
+        incoming.folder = Some("Work".to_string());
+        incoming.notes = Some("из импорта".to_string());
+        incoming.created_at = 500;
+        incoming.custom_fields = existing.custom_fields.clone();
+        incoming.attachments = existing.attachments.clone();
+        incoming.history.push(PasswordHistoryItem {
+            password: "oldest".to_string(),
+            changed_at: 100,
+        });
+
+        let merged = merge(&existing, incoming, 5_000);
+
+        assert_eq!(merged.password, "new");
+        assert_eq!(merged.url, existing.url);
+        assert_eq!(merged.folder.as_deref(), Some("Work"));
+        assert_eq!(merged.notes.as_deref(), Some("своя заметка\n\nиз импорта"));
+        assert_eq!(history(&merged), vec![("oldest", 100), ("old", 3_000)]);
+        assert_eq!(merged.custom_fields.len(), 1);
+        assert_eq!(merged.attachments.len(), 1);
+        assert_eq!(merged.created_at, 500);
+        assert_eq!(merged.updated_at, 5_000);
+    }
+
+    #[test]
+    fn merge_keeps_existing_password_when_it_is_newer() {
+        let merged = merge(&login("current", 3_000), login("stale", 2_000), 5_000);
+
+        assert_eq!(merged.password, "current");
+        assert_eq!(history(&merged), vec![("stale", 3_000)]);
+    }
+
+    #[test]
+    fn overwrite_moves_old_password_to_history() {
+        let mut existing = login("old", 2_000);
+        existing.notes = Some("своя заметка".to_string());
+        existing.history.push(PasswordHistoryItem {
+            password: "older".to_string(),
+            changed_at: 1_500,
+        });
+        let mut incoming = login("new", 3_000);
+        incoming.created_at = 0;
+
+        let overwritten = overwrite(&existing, incoming, 5_000);
+
+        assert_eq!(overwritten.password, "new");
+        assert_eq!(overwritten.notes, None);
+        assert_eq!(
+            history(&overwritten),
+            vec![("older", 1_500), ("old", 5_000)]
+        );
+        // Неизвестное время создания не затирает известное
+        assert_eq!(overwritten.created_at, 1_000);
+        assert_eq!(overwritten.updated_at, 5_000);
+    }
+
+    #[test]
+    fn overwrite_with_same_password_keeps_history() {
+        let overwritten = overwrite(&login("same", 2_000), login("same", 3_000), 5_000);
+
+        assert!(overwritten.history.is_empty());
+    }
+}
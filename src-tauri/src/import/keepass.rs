@@ -0,0 +1,264 @@
+use ::keepass::db::{fields, EntryRef, GroupId, GroupRef};
+use ::keepass::{Database, DatabaseKey};
+use anyhow::{Context, Result};
+use secrecy::{ExposeSecret, SecretBox as Secret};
+use std::fs::File;
+use std::path::Path;
+
+use super::{non_empty, ParsedImport, SkippedItem};
+use crate::db::operations::{
+    AttachmentRecord, CustomField, EntryRecord, EntryType, PasswordHistoryItem,
+};
+use crate::export::kdbx::ENTRY_TYPE_FIELD;
+use crate::export::TYPED_FIELDS;
+
+/// Поле, в котором KeePass 2.47+ хранит секрет TOTP
+const KEEPASS_TOTP_FIELD: &str = "TimeOtp-Secret-Base32";
+
+/// Открывает файл KDBX 3.1/4 паролем и (необязательно) ключевым файлом
+pub fn open(path: &Path, password: &Secret<String>, key_file: Option<&Path>) -> Result<Database> {
+    let mut key = DatabaseKey::new();
+
+    let password = password.expose_secret();
+    if !password.is_empty() {
+        key = key.with_password(password);
+    }
+
+    if let Some(key_file) = key_file {
+        let mut file = File::open(key_file)
+            .with_context(|| format!("Failed to open key file: {:?}", key_file))?;
+        key = key
+            .with_keyfile(&mut file)
+            .context("Failed to read key file")?;
+    }
+
+    if key.is_empty() {
+        anyhow::bail!("Password or key file is required");
+    }
+
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open KeePass file: {:?}", path))?;
+
+    Database::open(&mut file, key).context("Wrong credentials or corrupted KeePass file")
+}
+
+/// Переносит группы и записи KeePass в формат хранилища.
+/// Корзина пропускается, путь группы («Work/Servers») становится папкой.
+pub fn parse(database: &Database) -> ParsedImport {
+    let mut parsed = ParsedImport::default();
+    let recycle_bin = database.recycle_bin().map(|group| group.id());
+
+    let root = database.root();
+    for entry in root.entries() {
+        parsed.records.push(convert_entry(&entry, None));
+    }
+    for group in root.groups() {
+        collect_group(&group, String::new(), recycle_bin, &mut parsed);
+    }
+
+    parsed
+}
+
+fn collect_group(
+    group: &GroupRef<'_>,
+    parent_path: String,
+    recycle_bin: Option<GroupId>,
+    parsed: &mut ParsedImport,
+) {
+    if Some(group.id()) == recycle_bin {
+        for entry in group.entries() {
+            parsed.skipped.push(SkippedItem {
+                name: entry.get_title().unwrap_or_default().to_string(),
+                reason: "Entry is in the recycle bin".to_string(),
+            });
+        }
+        return;
+    }
+
+    let path = if parent_path.is_empty() {
+        group.name.clone()
+    } else {
+        format!("{parent_path}/{}", group.name)
+    };
+
+    for entry in group.entries() {
+        parsed
+            .records
+            .push(convert_entry(&entry, Some(path.clone())));
+    }
+    for child in group.groups() {
+        collect_group(&child, path.clone(), recycle_bin, parsed);
+    }
+}
+
+fn convert_entry(entry: &EntryRef<'_>, folder: Option<String>) -> EntryRecord {
+    // Тип записи и её поля пишет экспорт в KDBX; у остальных файлов все записи — логины
+    let entry_type = entry
+        .get(ENTRY_TYPE_FIELD)
+        .and_then(EntryType::from_column)
+        .unwrap_or_default();
+    let typed_fields: Vec<_> = TYPED_FIELDS
+        .iter()
+        .filter(|field| field.entry_type == entry_type)
+        .collect();
+
+    let mut custom_fields: Vec<CustomField> = entry
+        .fields
+        .iter()
+        .filter(|(name, _)| {
+            !fields::KNOWN_FIELDS.contains(&name.as_str())
+                && name.as_str() != fields::OTP
+                && name.as_str() != KEEPASS_TOTP_FIELD
+                && name.as_str() != ENTRY_TYPE_FIELD
+                && !typed_fields
+                    .iter()
+                    .any(|field| field.label == name.as_str())
+        })
+        .map(|(name, value)| CustomField {
+            name: name.clone(),
+            value: value.get().clone(),
+            hidden: value.is_protected(),
+        })
+        .collect();
+    // HashMap не сохраняет порядок — сортируем, чтобы импорт был детерминированным
+    custom_fields.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let totp = entry
+        .get(fields::OTP)
+        .or_else(|| entry.get(KEEPASS_TOTP_FIELD))
+        .map(str::to_string);
+
+    let mut attachments: Vec<AttachmentRecord> = entry
+        .attachments_named()
+        .map(|(name, attachment)| AttachmentRecord {
+            name: name.to_string(),
+            data: attachment.data.get().clone(),
+            created_at: 0,
+        })
+        .collect();
+    attachments.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let password = entry.get_password().unwrap_or_default().to_string();
+
+    let mut record = EntryRecord {
+        entry_type,
+        site: entry.get_title().unwrap_or_default().to_string(),
+        login: entry.get_username().unwrap_or_default().to_string(),
+        history: password_history(entry, &password),
+        password,
+        url: non_empty(entry.get_url().map(str::to_string)),
+        notes: non_empty(entry.get(fields::NOTES).map(str::to_string)),
+        folder,
+        totp: non_empty(totp),
+        custom_fields,
+        attachments,
+        card: None,
+        identity: None,
+        ssh_key: None,
+        created_at: timestamp(entry.times.creation),
+        updated_at: timestamp(entry.times.last_modification),
+    };
+
+    for field in typed_fields {
+        if let Some(value) = entry.get(field.label) {
+            field.set(&mut record, value.to_string());
+        }
+    }
+    match entry_type {
+        EntryType::Card => {
+            record.card.get_or_insert_with(Default::default);
+        }
+        EntryType::Identity => {
+            record.identity.get_or_insert_with(Default::default);
+        }
+        EntryType::SshKey => {
+            record.ssh_key.get_or_insert_with(Default::default);
+        }
+        EntryType::Login | EntryType::SecureNote => {}
+    }
+
+    record
+}
+
+/// Собирает прежние пароли из снимков истории записи.
+/// Порядок снимков в файле не гарантирован (KeePass и KeePassXC пишут их
+/// от старых к новым), поэтому они упорядочиваются по времени изменения.
+/// Снимки без смены пароля пропускаются.
+fn password_history(entry: &EntryRef<'_>, current_password: &str) -> Vec<PasswordHistoryItem> {
+    let Some(history) = entry.history.as_ref() else {
+        return Vec::new();
+    };
+
+    let mut snapshots: Vec<(i64, &str)> = history
+        .get_entries()
+        .iter()
+        .map(|snapshot| {
+            (
+                timestamp(snapshot.times.last_modification),
+                snapshot.get_password().unwrap_or_default(),
+            )
+        })
+        .collect();
+    snapshots.sort_by_key(|(modified_at, _)| *modified_at);
+
+    let mut items = Vec::new();
+    let mut newer_password = current_password;
+    let mut newer_changed_at = timestamp(entry.times.last_modification);
+
+    for (modified_at, password) in snapshots.into_iter().rev() {
+        if password != newer_password && !password.is_empty() {
+            items.push(PasswordHistoryItem {
+                password: password.to_string(),
+                changed_at: newer_changed_at,
+            });
+        }
+        newer_password = password;
+        newer_changed_at = modified_at;
+    }
+
+    items.reverse();
+    items
+}
+
+fn timestamp(time: Option<chrono::NaiveDateTime>) -> i64 {
+    time.map(|t| t.and_utc().timestamp()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// База в формате KeePassXC: запись «Forum» создана с паролем `first`,
+    /// затем пароль менялся на `second` и `current`, а после этого правились
+    /// только заметки. История в файле идёт от старых снимков к новым.
+    const KEEPASSXC_HISTORY: &[u8] = include_bytes!("../../tests/fixtures/keepassxc_history.kdbx");
+
+    #[test]
+    fn keepassxc_history_is_ordered_by_modification_time() {
+        let database = Database::parse(
+            KEEPASSXC_HISTORY,
+            DatabaseKey::new().with_password("fixture"),
+        )
+        .unwrap();
+        let parsed = parse(&database);
+
+        assert_eq!(parsed.records.len(), 1);
+        let record = &parsed.records[0];
+        assert_eq!(record.site, "Forum");
+        assert_eq!(record.password, "current");
+        assert_eq!(record.updated_at, 1_630_000_000);
+        assert_eq!(
+            record.history,
+            vec![
+                PasswordHistoryItem {
+                    password: "first".to_string(),
+                    changed_at: 1_610_000_000,
+                },
+                PasswordHistoryItem {
+                    password: "second".to_string(),
+                    changed_at: 1_620_000_000,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::{non_empty, site_name, ParsedImport, SkippedItem};
+use crate::db::operations::{EntryRecord, EntryType};
+
+/// Адрес, которым LastPass помечает защищённые заметки
+const SECURE_NOTE_URL: &str = "http://sn";
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct CsvRow {
+    url: String,
+    username: String,
+    password: String,
+    totp: String,
+    extra: String,
+    name: String,
+    grouping: String,
+}
+
+/// Разбирает CSV-экспорт LastPass (`url,username,password,totp,extra,name,grouping,fav`).
+/// `extra` становится заметкой, `grouping` — папкой. Строки с адресом `http://sn` —
+/// защищённые заметки, их текст тоже лежит в `extra`.
+pub fn parse_csv(contents: &str) -> Result<ParsedImport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let headers = reader.headers().context("Failed to read CSV header")?;
+    if !headers.iter().any(|h| h == "grouping") {
+        anyhow::bail!("File is not a LastPass CSV export");
+    }
+
+    let mut parsed = ParsedImport::default();
+    for (line, row) in reader.deserialize::<CsvRow>().enumerate() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                parsed.skipped.push(SkippedItem {
+                    name: format!("Row {}", line + 2),
+                    reason: format!("Invalid CSV row: {e}"),
+                });
+                continue;
+            }
+        };
+
+        if row.url == SECURE_NOTE_URL {
+            parsed.records.push(EntryRecord {
+                entry_type: EntryType::SecureNote,
+                site: row.name,
+                notes: non_empty(Some(row.extra)),
+                folder: folder_path(&row.grouping),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let url = non_empty(Some(row.url));
+        let site = non_empty(Some(row.name))
+            .or_else(|| url.as_deref().and_then(site_name))
+            .unwrap_or_default();
+
+        parsed.records.push(EntryRecord {
+            site,
+            login: row.username,
+            password: row.password,
+            url,
+            notes: non_empty(Some(row.extra)),
+            folder: folder_path(&row.grouping),
+            totp: non_empty(Some(row.totp)),
+            ..Default::default()
+        });
+    }
+
+    Ok(parsed)
+}
+
+/// Вложенные папки LastPass разделяет обратной косой чертой
+fn folder_path(grouping: &str) -> Option<String> {
+    let path = grouping
+        .split('\\')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    non_empty(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Экспорт LastPass: логин во вложенной папке, запись без имени и защищённая заметка
+    const LASTPASS_EXPORT: &str = include_str!("../../tests/fixtures/lastpass_export.csv");
+
+    #[test]
+    fn rows_become_logins() {
+        let parsed = parse_csv(LASTPASS_EXPORT).unwrap();
+
+        assert_eq!(parsed.records.len(), 3);
+
+        let github = &parsed.records[0];
+        assert_eq!(github.entry_type, EntryType::Login);
+        assert_eq!(github.site, "GitHub");
+        assert_eq!(github.login, "octocat");
+        assert_eq!(github.password, "current");
+        assert_eq!(github.url.as_deref(), Some("https://github.com/login"));
+        assert_eq!(github.totp.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+        assert_eq!(github.notes.as_deref(), Some("Рабочий аккаунт"));
+        assert_eq!(github.folder.as_deref(), Some("Work/Dev"));
+
+        // Без имени сайтом становится хост адреса
+        let mail = &parsed.records[1];
+        assert_eq!(mail.site, "mail.example.com");
+        assert_eq!(mail.password, "pa,ss");
+        assert_eq!(mail.folder, None);
+        assert_eq!(mail.totp, None);
+    }
+
+    #[test]
+    fn secure_notes_become_notes() {
+        let parsed = parse_csv(LASTPASS_EXPORT).unwrap();
+
+        let note = &parsed.records[2];
+        assert_eq!(note.entry_type, EntryType::SecureNote);
+        assert_eq!(note.site, "Server notes");
+        assert_eq!(
+            note.notes.as_deref(),
+            Some("NoteType:Server\nHostname:10.0.0.1")
+        );
+        assert_eq!(note.folder.as_deref(), Some("Work"));
+        assert_eq!(note.url, None);
+        assert!(parsed.skipped.is_empty());
+    }
+
+    #[test]
+    fn other_csv_is_rejected() {
+        let error = parse_csv("name,url,username,password\n").err().unwrap();
+
+        assert_eq!(error.to_string(), "File is not a LastPass CSV export");
+    }
+
+    #[test]
+    fn folder_path_drops_empty_parts() {
+        assert_eq!(folder_path(" Work \\\\Dev\\").as_deref(), Some("Work/Dev"));
+        assert_eq!(folder_path(""), None);
+    }
+}
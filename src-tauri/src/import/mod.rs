@@ -0,0 +1,445 @@
+pub mod bitwarden;
+pub mod browser;
+pub mod dedup;
+pub mod keepass;
+pub mod lastpass;
+pub mod onepassword;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::db::operations::{self, EntryRecord};
+use crate::db::Vault;
+
+/// Поддерживаемые форматы импорта
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSource {
+    BitwardenJson,
+    BitwardenCsv,
+    ChromiumCsv,
+    FirefoxCsv,
+    #[serde(rename = "onepassword_1pux")]
+    OnePassword1pux,
+    LastpassCsv,
+}
+
+impl ImportSource {
+    /// Название фильтра и расширения файлов для диалога выбора
+    pub fn file_filter(&self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            ImportSource::BitwardenJson => ("Bitwarden JSON", &["json"]),
+            ImportSource::BitwardenCsv => ("Bitwarden CSV", &["csv"]),
+            ImportSource::ChromiumCsv => ("Chrome / Edge CSV", &["csv"]),
+            ImportSource::FirefoxCsv => ("Firefox CSV", &["csv"]),
+            ImportSource::OnePassword1pux => ("1Password", &["1pux"]),
+            ImportSource::LastpassCsv => ("LastPass CSV", &["csv"]),
+        }
+    }
+}
+
+/// Результат разбора файла: записи для вставки и пропущенные элементы
+#[derive(Default)]
+pub struct ParsedImport {
+    pub records: Vec<EntryRecord>,
+    pub skipped: Vec<SkippedItem>,
+}
+
+/// Элемент, который не удалось перенести в хранилище
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedItem {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Итог импорта
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    /// Добавлено новых записей (включая «оставить обе»)
+    pub imported: usize,
+    /// Заменено существующих записей
+    pub overwritten: usize,
+    /// Объединено с существующими записями
+    pub merged: usize,
+    /// Пропущено как дубликаты
+    pub duplicates: usize,
+    pub skipped: Vec<SkippedItem>,
+}
+
+/// Что сделать с импортируемой записью, совпавшей с существующей
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Не импортировать запись
+    Skip,
+    /// Заменить поля существующей записи импортированными
+    Overwrite,
+    /// Добавить запись рядом с существующей
+    KeepBoth,
+    /// Дополнить существующую запись недостающими полями
+    Merge,
+}
+
+/// Решение вызывающей стороны по конфликту с номером записи `index`
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ConflictDecision {
+    pub index: usize,
+    pub resolution: ConflictResolution,
+}
+
+/// Запись из файла, совпадающая с уже существующей в хранилище
+/// по хосту адреса и логину
+#[derive(Debug, Serialize)]
+pub struct ImportConflict {
+    /// Номер записи в импортируемом файле
+    pub index: usize,
+    pub site: String,
+    pub login: String,
+    pub url: Option<String>,
+    pub existing_id: u64,
+    pub existing_site: String,
+    pub existing_login: String,
+    pub same_password: bool,
+    /// Решение, которое будет применено, если вызывающая сторона не выберет другое
+    pub resolution: ConflictResolution,
+}
+
+/// Предпросмотр импорта (dry run): что будет добавлено и с чем оно конфликтует
+#[derive(Debug, Serialize)]
+pub struct ImportPreview {
+    pub entries: usize,
+    pub folders: usize,
+    pub custom_fields: usize,
+    pub history_items: usize,
+    pub attachments: usize,
+    pub skipped: Vec<SkippedItem>,
+    pub conflicts: Vec<ImportConflict>,
+}
+
+/// Результат импорта: предпросмотр без изменений либо итог записи в хранилище
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ImportOutcome {
+    Preview(ImportPreview),
+    Imported(ImportReport),
+}
+
+/// Читает и разбирает файл в указанном формате
+pub fn parse_file(source: ImportSource, path: &Path) -> Result<ParsedImport> {
+    let data =
+        std::fs::read(path).with_context(|| format!("Failed to read import file: {:?}", path))?;
+
+    match source {
+        ImportSource::OnePassword1pux => onepassword::parse_1pux(&data),
+        ImportSource::BitwardenJson => bitwarden::parse_json(text(&data)?),
+        ImportSource::BitwardenCsv => bitwarden::parse_csv(text(&data)?),
+        ImportSource::ChromiumCsv => browser::parse_chromium_csv(text(&data)?),
+        ImportSource::FirefoxCsv => browser::parse_firefox_csv(text(&data)?),
+        ImportSource::LastpassCsv => lastpass::parse_csv(text(&data)?),
+    }
+}
+
+/// Текстовое содержимое файла импорта без UTF-8 BOM, который пишут многие экспортёры
+fn text(data: &[u8]) -> Result<&str> {
+    let contents = std::str::from_utf8(data).context("Import file is not valid UTF-8")?;
+    Ok(contents.trim_start_matches('\u{feff}'))
+}
+
+/// Считает, что будет импортировано, ничего не меняя в хранилище
+pub fn preview(vault: &Vault, parsed: &ParsedImport) -> Result<ImportPreview> {
+    let now = operations::unix_now();
+    let existing = vault
+        .identified_records()
+        .context("Failed to list existing entries")?;
+    let mut matcher = Matcher::new(&existing);
+
+    let mut conflicts = Vec::new();
+    for (position, record) in parsed.records.iter().enumerate() {
+        let mut record = record.clone();
+        fill_timestamps(&mut record, now);
+
+        let (conflict, _) = matcher.resolve(position, record, None, now, None)?;
+        conflicts.extend(conflict);
+    }
+
+    let mut folders: Vec<&str> = parsed
+        .records
+        .iter()
+        .filter_map(|record| record.folder.as_deref())
+        .collect();
+    folders.sort_unstable();
+    folders.dedup();
+
+    Ok(ImportPreview {
+        entries: parsed.records.len(),
+        folders: folders.len(),
+        custom_fields: parsed.records.iter().map(|r| r.custom_fields.len()).sum(),
+        history_items: parsed.records.iter().map(|r| r.history.len()).sum(),
+        attachments: parsed.records.iter().map(|r| r.attachments.len()).sum(),
+        skipped: parsed.skipped.clone(),
+        conflicts,
+    })
+}
+
+/// Переносит разобранные записи в хранилище одной транзакцией.
+/// Для совпавших записей применяется решение из `decisions`,
+/// а если его нет — предложенное в предпросмотре.
+pub fn commit(
+    vault: &Vault,
+    parsed: ParsedImport,
+    decisions: &[ConflictDecision],
+) -> Result<ImportReport> {
+    let now = operations::unix_now();
+    let decisions: HashMap<usize, ConflictResolution> = decisions
+        .iter()
+        .map(|decision| (decision.index, decision.resolution))
+        .collect();
+
+    let existing = vault
+        .identified_records()
+        .context("Failed to list existing entries")?;
+    let mut matcher = Matcher::new(&existing);
+    let load_full = |id| full_record(vault, id);
+
+    let mut new_records = Vec::new();
+    let (mut overwritten, mut merged, mut duplicates) = (0, 0, 0);
+
+    for (position, mut record) in parsed.records.into_iter().enumerate() {
+        fill_timestamps(&mut record, now);
+
+        let decision = decisions.get(&position).copied();
+        let (_, outcome) = matcher.resolve(position, record, decision, now, Some(&load_full))?;
+        match outcome {
+            Outcome::New(record) => new_records.push(*record),
+            Outcome::Duplicate => duplicates += 1,
+            Outcome::Overwritten => overwritten += 1,
+            Outcome::Merged => merged += 1,
+        }
+    }
+
+    let ids = vault
+        .apply_records(&new_records, &matcher.replacements)
+        .context("Failed to import entries")?;
+
+    Ok(ImportReport {
+        imported: ids.len(),
+        overwritten,
+        merged,
+        duplicates,
+        skipped: parsed.skipped,
+    })
+}
+
+/// Что стало с записью из файла
+enum Outcome {
+    /// Добавляется новой записью
+    New(Box<EntryRecord>),
+    Duplicate,
+    Overwritten,
+    Merged,
+}
+
+/// Сопоставляет записи из файла с существующими. Несколько записей файла
+/// могут совпасть с одной существующей — изменения копятся, и следующая
+/// запись сравнивается уже с изменённой. Предпросмотр и импорт идут
+/// через него оба, поэтому предложенное решение совпадает с применённым.
+struct Matcher<'a> {
+    existing: &'a [(u64, EntryRecord)],
+    index: HashMap<dedup::MatchKey, usize>,
+    /// Новое состояние изменённых существующих записей
+    replacements: Vec<(u64, EntryRecord)>,
+    replaced_positions: HashMap<u64, usize>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(existing: &'a [(u64, EntryRecord)]) -> Self {
+        Self {
+            existing,
+            index: dedup::index(existing),
+            replacements: Vec::new(),
+            replaced_positions: HashMap::new(),
+        }
+    }
+
+    /// Применяет к записи решение `decision` или предложенное.
+    /// Существующие записи прочитаны без вложений, а слияние и замена
+    /// их сохраняют: `load_full` читает запись целиком; без него
+    /// (в предпросмотре) берётся запись без вложений.
+    fn resolve(
+        &mut self,
+        position: usize,
+        record: EntryRecord,
+        decision: Option<ConflictResolution>,
+        now: i64,
+        load_full: Option<&dyn Fn(u64) -> Result<EntryRecord>>,
+    ) -> Result<(Option<ImportConflict>, Outcome)> {
+        let Some(&existing_position) =
+            dedup::match_key(&record).and_then(|key| self.index.get(&key))
+        else {
+            return Ok((None, Outcome::New(Box::new(record))));
+        };
+        let (id, original) = &self.existing[existing_position];
+        let id = *id;
+        let replaced = self.replaced_positions.get(&id).copied();
+        let current = match replaced {
+            Some(slot) => &self.replacements[slot].1,
+            None => original,
+        };
+
+        let suggested = dedup::suggest(current, &record);
+        let conflict = ImportConflict {
+            index: position,
+            site: record.site.clone(),
+            login: record.login.clone(),
+            url: record.url.clone(),
+            existing_id: id,
+            existing_site: current.site.clone(),
+            existing_login: current.login.clone(),
+            same_password: current.password == record.password,
+            resolution: suggested,
+        };
+
+        let (updated, outcome) = match decision.unwrap_or(suggested) {
+            ConflictResolution::Skip => return Ok((Some(conflict), Outcome::Duplicate)),
+            ConflictResolution::KeepBoth => {
+                return Ok((Some(conflict), Outcome::New(Box::new(record))))
+            }
+            ConflictResolution::Overwrite => {
+                let updated = match (replaced, load_full) {
+                    (None, Some(load_full)) => dedup::overwrite(&load_full(id)?, record, now),
+                    _ => dedup::overwrite(current, record, now),
+                };
+                (updated, Outcome::Overwritten)
+            }
+            ConflictResolution::Merge => {
+                let updated = match (replaced, load_full) {
+                    (None, Some(load_full)) => dedup::merge(&load_full(id)?, record, now),
+                    _ => dedup::merge(current, record, now),
+                };
+                (updated, Outcome::Merged)
+            }
+        };
+
+        match replaced {
+            Some(slot) => self.replacements[slot].1 = updated,
+            None => {
+                self.replaced_positions.insert(id, self.replacements.len());
+                self.replacements.push((id, updated));
+            }
+        }
+
+        Ok((Some(conflict), outcome))
+    }
+}
+
+/// Существующая запись со всеми полями, включая вложения
+fn full_record(vault: &Vault, id: u64) -> Result<EntryRecord> {
+    vault
+        .entry_records(&[id])
+        .context("Failed to read existing entry")?
+        .pop()
+        .with_context(|| format!("Entry {id} not found"))
+}
+
+/// Подставляет текущее время вместо неизвестных меток
+fn fill_timestamps(record: &mut EntryRecord, now: i64) {
+    if record.created_at == 0 {
+        record.created_at = now;
+    }
+    if record.updated_at == 0 {
+        record.updated_at = record.created_at;
+    }
+    for attachment in &mut record.attachments {
+        if attachment.created_at == 0 {
+            attachment.created_at = record.created_at;
+        }
+    }
+}
+
+/// Переводит дату RFC 3339 («2024-01-31T12:00:00.000Z») в секунды Unix
+pub(crate) fn parse_rfc3339(value: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|date| date.timestamp())
+}
+
+/// Возвращает `None` для пустых строк
+pub(crate) fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+/// Извлекает хост из адреса в нижнем регистре: «https://user@Example.com:8080/x» → «example.com»
+pub(crate) fn url_host(url: &str) -> Option<String> {
+    let url = url.trim();
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+
+    let host = if let Some(ipv6) = host_port.strip_prefix('[') {
+        ipv6.split(']').next().unwrap_or_default()
+    } else {
+        host_port.split(':').next().unwrap_or_default()
+    };
+
+    let host = host.trim_end_matches('.').to_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+/// Название записи по адресу сайта: хост без префикса «www.»
+pub(crate) fn site_name(url: &str) -> Option<String> {
+    url_host(url).map(|host| match host.strip_prefix("www.") {
+        Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+        _ => host,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::utils::fs::TestDir;
+    use secrecy::SecretBox as Secret;
+
+    #[test]
+    fn overwrite_keeps_stored_attachments() {
+        let dir = TestDir::new("import-overwrite");
+        let vault = db::create_new_vault(
+            dir.join("vault.db"),
+            Secret::new(Box::new("master".to_string())),
+        )
+        .unwrap();
+        vault
+            .unlock(Secret::new(Box::new("master".to_string())))
+            .unwrap();
+        let id = vault.add_password("github.com", "octocat", "old").unwrap();
+        std::fs::write(dir.join("recovery.txt"), b"codes").unwrap();
+        vault.attach_file(id, &dir.join("recovery.txt")).unwrap();
+
+        let parsed = ParsedImport {
+            records: vec![EntryRecord {
+                site: "github.com".to_string(),
+                login: "octocat".to_string(),
+                password: "new".to_string(),
+                ..EntryRecord::default()
+            }],
+            skipped: Vec::new(),
+        };
+        let decisions = [ConflictDecision {
+            index: 0,
+            resolution: ConflictResolution::Overwrite,
+        }];
+
+        let report = commit(&vault, parsed, &decisions).unwrap();
+
+        assert_eq!(report.overwritten, 1);
+        let record = full_record(&vault, id).unwrap();
+        assert_eq!(record.password, "new");
+        assert_eq!(record.attachments.len(), 1);
+        assert_eq!(record.attachments[0].name, "recovery.txt");
+        assert_eq!(record.attachments[0].data, b"codes");
+    }
+}
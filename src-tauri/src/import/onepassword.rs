@@ -0,0 +1,495 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::io::{Cursor, Read};
+
+use super::{non_empty, site_name, ParsedImport, SkippedItem};
+use crate::db::attachments::MAX_ATTACHMENT_SIZE;
+use crate::db::operations::{
+    AttachmentRecord, CustomField, EntryRecord, EntryType, PasswordHistoryItem,
+};
+
+/// Наибольший размер `export.data` после распаковки
+const MAX_EXPORT_DATA_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Категории элементов 1Password, которые переносятся как логины
+const CATEGORY_LOGIN: &str = "001";
+const CATEGORY_PASSWORD: &str = "005";
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Export {
+    accounts: Vec<Account>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Account {
+    vaults: Vec<VaultData>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct VaultData {
+    attrs: VaultAttrs,
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct VaultAttrs {
+    name: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct Item {
+    category_uuid: String,
+    state: String,
+    created_at: i64,
+    updated_at: i64,
+    details: Details,
+    overview: Overview,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct Details {
+    login_fields: Vec<LoginField>,
+    notes_plain: Option<String>,
+    sections: Vec<Section>,
+    password_history: Vec<HistoryItem>,
+    password: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct LoginField {
+    value: String,
+    name: String,
+    field_type: String,
+    designation: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Section {
+    fields: Vec<SectionField>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SectionField {
+    title: String,
+    id: String,
+    value: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct HistoryItem {
+    value: String,
+    time: i64,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Overview {
+    title: String,
+    url: String,
+    urls: Vec<OverviewUrl>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct OverviewUrl {
+    url: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct FileAttributes {
+    file_name: String,
+    document_id: String,
+}
+
+/// Разбирает архив 1Password `.1pux`: `export.data` с JSON и вложения в `files/`.
+/// Название сейфа становится папкой записи.
+pub fn parse_1pux(data: &[u8]) -> Result<ParsedImport> {
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(data)).context("File is not a 1Password 1PUX archive")?;
+
+    let export: Export = {
+        let file = archive
+            .by_name("export.data")
+            .context("File is not a 1Password 1PUX archive")?;
+        let contents =
+            read_limited(file, MAX_EXPORT_DATA_SIZE).context("Failed to read 1PUX export data")?;
+        serde_json::from_slice(&contents).context("Invalid 1PUX export data")?
+    };
+
+    let mut parsed = ParsedImport::default();
+    for vault in export
+        .accounts
+        .into_iter()
+        .flat_map(|account| account.vaults)
+    {
+        let folder = non_empty(Some(vault.attrs.name));
+
+        for item in vault.items {
+            let name = item.overview.title.clone();
+
+            if item.state == "archived" {
+                parsed.skipped.push(SkippedItem {
+                    name,
+                    reason: "Item is archived".to_string(),
+                });
+                continue;
+            }
+            if item.category_uuid != CATEGORY_LOGIN && item.category_uuid != CATEGORY_PASSWORD {
+                parsed.skipped.push(SkippedItem {
+                    name,
+                    reason: format!(
+                        "Unsupported item type: {}",
+                        category_name(&item.category_uuid)
+                    ),
+                });
+                continue;
+            }
+
+            let record = convert_item(item, folder.clone(), &mut archive, &mut parsed.skipped);
+            parsed.records.push(record);
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Переводит элемент в запись; вложения, которые не удалось прочитать,
+/// попадают в `skipped`
+fn convert_item(
+    item: Item,
+    folder: Option<String>,
+    archive: &mut zip::ZipArchive<Cursor<&[u8]>>,
+    skipped: &mut Vec<SkippedItem>,
+) -> EntryRecord {
+    let details = item.details;
+    let mut login = String::new();
+    let mut password = details.password.unwrap_or_default();
+    let mut totp = None;
+    let mut custom_fields = Vec::new();
+    let mut attachments = Vec::new();
+
+    for field in details.login_fields {
+        match field.designation.as_str() {
+            "username" => login = field.value,
+            "password" => password = field.value,
+            _ if !field.value.is_empty() => custom_fields.push(CustomField {
+                name: field.name,
+                value: field.value,
+                hidden: field.field_type == "P",
+            }),
+            _ => {}
+        }
+    }
+
+    for field in details
+        .sections
+        .into_iter()
+        .flat_map(|section| section.fields)
+    {
+        let name = if field.title.is_empty() {
+            field.id
+        } else {
+            field.title
+        };
+        let Some((kind, value)) = field.value.into_iter().next() else {
+            continue;
+        };
+
+        match kind.as_str() {
+            "totp" if totp.is_none() => totp = value.as_str().map(str::to_string),
+            "file" => {
+                let Ok(file) = serde_json::from_value::<FileAttributes>(value) else {
+                    continue;
+                };
+                match read_attachment(archive, &file) {
+                    Ok(data) => attachments.push(AttachmentRecord {
+                        name: file.file_name,
+                        data,
+                        created_at: item.created_at,
+                    }),
+                    Err(e) => skipped.push(SkippedItem {
+                        name: format!("{} / {}", item.overview.title, file.file_name),
+                        reason: format!("{e:#}"),
+                    }),
+                }
+            }
+            _ => {
+                let Some(value) = field_text(&value) else {
+                    continue;
+                };
+                custom_fields.push(CustomField {
+                    name,
+                    value,
+                    hidden: kind == "concealed",
+                });
+            }
+        }
+    }
+
+    let mut urls = item
+        .overview
+        .urls
+        .into_iter()
+        .map(|url| url.url)
+        .chain(std::iter::once(item.overview.url))
+        .filter(|url| !url.trim().is_empty());
+    let url = urls.next();
+    // В схеме хранилища один адрес на запись — остальные сохраняем полями
+    let mut extra_urls: Vec<String> = urls.filter(|u| Some(u) != url.as_ref()).collect();
+    extra_urls.dedup();
+    for (index, extra) in extra_urls.into_iter().enumerate() {
+        custom_fields.push(CustomField {
+            name: format!("URL {}", index + 2),
+            value: extra,
+            hidden: false,
+        });
+    }
+
+    let mut history: Vec<PasswordHistoryItem> = details
+        .password_history
+        .into_iter()
+        .filter(|old| !old.value.is_empty())
+        .map(|old| PasswordHistoryItem {
+            password: old.value,
+            changed_at: old.time,
+        })
+        .collect();
+    history.sort_by_key(|item| item.changed_at);
+
+    let site = non_empty(Some(item.overview.title))
+        .or_else(|| url.as_deref().and_then(site_name))
+        .unwrap_or_default();
+
+    EntryRecord {
+        entry_type: EntryType::Login,
+        site,
+        login,
+        password,
+        url,
+        notes: non_empty(details.notes_plain),
+        folder,
+        totp: non_empty(totp),
+        custom_fields,
+        history,
+        attachments,
+        card: None,
+        identity: None,
+        ssh_key: None,
+        created_at: item.created_at,
+        updated_at: item.updated_at,
+    }
+}
+
+/// Значение поля раздела как строка; составные поля (адрес, дата) пропускаются
+fn field_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => non_empty(Some(text.clone())),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::Object(object) => object
+            .get("email_address")
+            .and_then(|email| email.as_str())
+            .map(str::to_string),
+        _ => None,
+    }
+}
+
+/// Вложения лежат в архиве как `files/<documentId>__<fileName>`
+fn read_attachment(
+    archive: &mut zip::ZipArchive<Cursor<&[u8]>>,
+    file: &FileAttributes,
+) -> Result<Vec<u8>> {
+    if file.document_id.is_empty() {
+        anyhow::bail!("Attachment has no document ID");
+    }
+
+    let prefix = format!("files/{}__", file.document_id);
+    let path = archive
+        .file_names()
+        .filter_map(|name| name.ok())
+        .find(|name| name.starts_with(&prefix))
+        .context("Attachment is missing from the archive")?
+        .into_owned();
+
+    let entry = archive
+        .by_name(&path)
+        .context("Failed to open attachment")?;
+    read_limited(entry, MAX_ATTACHMENT_SIZE).context("Failed to read attachment")
+}
+
+/// Читает файл архива не больше `limit` байт. Размер из заголовка ZIP
+/// проверяется заранее, но ему нельзя верить: чтение тоже ограничено.
+fn read_limited(file: zip::read::ZipFile<'_, Cursor<&[u8]>>, limit: u64) -> Result<Vec<u8>> {
+    let too_large = || anyhow::anyhow!("File is larger than {} MiB", limit / 1024 / 1024);
+    if file.size() > limit {
+        return Err(too_large());
+    }
+
+    let mut data = Vec::new();
+    file.take(limit + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > limit {
+        return Err(too_large());
+    }
+
+    Ok(data)
+}
+
+fn category_name(category: &str) -> &'static str {
+    match category {
+        "002" => "credit card",
+        "003" => "secure note",
+        "004" => "identity",
+        "006" => "document",
+        "114" => "SSH key",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Архив 1Password: логин с разделами, вложением и историей, пароль,
+    /// карта и архивный элемент в сейфе «Private»; одного вложения в архиве нет
+    const ONEPASSWORD_EXPORT: &[u8] =
+        include_bytes!("../../tests/fixtures/onepassword_export.1pux");
+
+    #[test]
+    fn login_keeps_all_fields() {
+        let parsed = parse_1pux(ONEPASSWORD_EXPORT).unwrap();
+        let github = &parsed.records[0];
+
+        assert_eq!(github.site, "GitHub");
+        assert_eq!(github.login, "octocat");
+        assert_eq!(github.password, "current");
+        assert_eq!(github.url.as_deref(), Some("https://github.com/login"));
+        assert_eq!(github.notes.as_deref(), Some("Рабочий аккаунт"));
+        assert_eq!(github.folder.as_deref(), Some("Private"));
+        assert_eq!(
+            github.totp.as_deref(),
+            Some("otpauth://totp/GitHub:octocat?secret=JBSWY3DPEHPK3PXP")
+        );
+        assert_eq!(github.created_at, 1_673_341_200);
+        assert_eq!(github.updated_at, 1_716_206_400);
+
+        assert_eq!(
+            github.custom_fields,
+            vec![
+                CustomField {
+                    name: "remember".to_string(),
+                    value: "✓".to_string(),
+                    hidden: false,
+                },
+                CustomField {
+                    name: "Recovery code".to_string(),
+                    value: "1234-5678".to_string(),
+                    hidden: true,
+                },
+                CustomField {
+                    name: "team".to_string(),
+                    value: "Core".to_string(),
+                    hidden: false,
+                },
+                CustomField {
+                    name: "Backup email".to_string(),
+                    value: "backup@example.com".to_string(),
+                    hidden: false,
+                },
+                CustomField {
+                    name: "URL 2".to_string(),
+                    value: "https://gist.github.com".to_string(),
+                    hidden: false,
+                },
+            ]
+        );
+
+        let history: Vec<_> = github
+            .history
+            .iter()
+            .map(|item| (item.password.as_str(), item.changed_at))
+            .collect();
+        assert_eq!(
+            history,
+            vec![("first", 1_686_817_800), ("second", 1_709_287_200)]
+        );
+    }
+
+    #[test]
+    fn attachments_are_read_from_files() {
+        let parsed = parse_1pux(ONEPASSWORD_EXPORT).unwrap();
+        let github = &parsed.records[0];
+
+        assert_eq!(github.attachments.len(), 1);
+        assert_eq!(github.attachments[0].name, "recovery.txt");
+        assert_eq!(github.attachments[0].data, b"RECOVERY-12345");
+        assert_eq!(github.attachments[0].created_at, github.created_at);
+
+        let missing = parsed
+            .skipped
+            .iter()
+            .find(|item| item.name == "GitHub / missing.pdf")
+            .unwrap();
+        assert_eq!(missing.reason, "Attachment is missing from the archive");
+    }
+
+    #[test]
+    fn password_items_become_logins() {
+        let parsed = parse_1pux(ONEPASSWORD_EXPORT).unwrap();
+
+        assert_eq!(parsed.records.len(), 2);
+        let wifi = &parsed.records[1];
+        assert_eq!(wifi.site, "Home Wi-Fi");
+        assert_eq!(wifi.password, "wifi-secret");
+        assert_eq!(wifi.url, None);
+    }
+
+    #[test]
+    fn unsupported_and_archived_items_are_skipped() {
+        let parsed = parse_1pux(ONEPASSWORD_EXPORT).unwrap();
+        let skipped: Vec<_> = parsed
+            .skipped
+            .iter()
+            .map(|item| (item.name.as_str(), item.reason.as_str()))
+            .collect();
+
+        assert_eq!(
+            skipped,
+            vec![
+                (
+                    "GitHub / missing.pdf",
+                    "Attachment is missing from the archive"
+                ),
+                ("Visa", "Unsupported item type: credit card"),
+                ("Old forum", "Item is archived"),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let error = parse_1pux(b"not a zip archive").err().unwrap();
+
+        assert_eq!(error.to_string(), "File is not a 1Password 1PUX archive");
+    }
+
+    #[test]
+    fn read_limited_stops_at_limit() {
+        let mut archive = zip::ZipArchive::new(Cursor::new(ONEPASSWORD_EXPORT)).unwrap();
+        let file = archive.by_name("files/docabc__recovery.txt").unwrap();
+
+        assert!(read_limited(file, 4).is_err());
+
+        let file = archive.by_name("files/docabc__recovery.txt").unwrap();
+        assert_eq!(read_limited(file, 14).unwrap(), b"RECOVERY-12345");
+    }
+}
@@ -1,21 +1,42 @@
 use anyhow::anyhow;
-use secrecy::SecretBox as Secret;
+use secrecy::{ExposeSecret, SecretBox as Secret};
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tauri::State;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 use tauri_plugin_updater::UpdaterExt;
 use serde::Serialize;
 
+mod audit;
 mod db;
+mod export;
+mod import;
 //mod utils;
-use utils::settings;
+use utils::{memory, settings, strength};
 pub mod utils;
 
 use db::Vault;
 
 // Состояние, которое будет храниться в Tauri
 struct AppState {
-    vault: Arc<Mutex<Option<Vault>>>,
+    /// Открытые хранилища по идентификатору сессии
+    vaults: Arc<Mutex<HashMap<String, Arc<Vault>>>>,
+    protection: memory::ProtectionStatus,
+    /// Ответы сервиса проверки утечек, общие для всех хранилищ
+    breach_cache: Arc<Mutex<audit::range_api::RangeCache>>,
+}
+
+/// Открытое хранилище сессии
+fn session_vault(state: &AppState, session: &str) -> Result<Arc<Vault>, String> {
+    state
+        .vaults
+        .lock()
+        .unwrap()
+        .get(session)
+        .cloned()
+        .ok_or_else(|| "Хранилище не открыто".to_string())
 }
 
 #[derive(Serialize)]
@@ -45,17 +66,42 @@ async fn update_settings(theme: String, vault_folder_path: String) -> Result<(),
     Ok(())
 }
 
-/// Создаёт новое зашифрованное хранилище
+/// Оценивает стойкость пароля для подсказок при вводе.
+/// `user_inputs` — слова, которые легко угадать (логин, сайт, имя хранилища).
+#[tauri::command]
+async fn estimate_password_strength(
+    password: String,
+    user_inputs: Option<Vec<String>>,
+) -> Result<strength::StrengthReport, String> {
+    let user_inputs = user_inputs.unwrap_or_default();
+    let user_inputs: Vec<&str> = user_inputs.iter().map(String::as_str).collect();
+
+    Ok(strength::estimate(&password, &user_inputs))
+}
+
+/// Создаёт новое зашифрованное хранилище.
+/// Для слабого мастер-пароля возвращает его оценку как предупреждение,
+/// а если в настройках включена блокировка — отказывает в создании.
 #[tauri::command]
-async fn create_vault(storage_name: String, password: String) -> Result<(), String> {
+async fn create_vault(
+    storage_name: String,
+    password: String,
+) -> Result<Option<strength::StrengthReport>, String> {
     let password = Secret::new(Box::new(password));
 
     tauri::async_runtime::spawn_blocking(move || {
         let settings =
             settings::AppSettings::load().map_err(|_| anyhow!("Failed to load settings"))?;
 
-        let vault_dir = PathBuf::from(&settings.vault_folder_path);
-        let storage_path = vault_dir.join(format!("{storage_name}.db"));
+        let weak = strength::check_master_password(
+            password.expose_secret(),
+            &storage_name,
+            &settings.master_password,
+        )?;
+
+        let storage_path = vault_registry(&settings)
+            .folder_vault(&storage_name)
+            .map_err(|e| anyhow!("Invalid storage name: {}", e))?;
 
         if db::vault_exists(&storage_path) {
             return Err(anyhow!("Storage already exists"));
@@ -64,131 +110,1406 @@ async fn create_vault(storage_name: String, password: String) -> Result<(), Stri
         db::create_new_vault(storage_path, password)
             .map_err(|e| anyhow!("Failed to create vault: {}", e))?;
 
-        Ok(())
+        Ok(weak)
     })
     .await
     .map_err(|_| "Internal error".to_string())?
     .map_err(|e| {
-        if e.to_string().contains("Storage already exists") {
+        let message = e.to_string();
+        if message.contains("Storage already exists") {
             "Storage with this name already exists".to_string()
+        } else if message.starts_with("Invalid storage name")
+            || message.starts_with("Master password is too weak")
+        {
+            message
         } else {
             "Failed to create storage".to_string()
         }
     })
 }
 
-/// Возвращает список доступных хранилищ
+/// Возвращает хранилища из основной папки и добавленных мест
+/// с источником, временем последнего открытия и размером файла
 #[tauri::command]
-async fn populate_list() -> Result<Vec<String>, String> {
-    tauri::async_runtime::spawn_blocking(|| -> Result<Vec<String>, String> {
+async fn populate_list() -> Result<Vec<db::registry::ListedVault>, String> {
+    tauri::async_runtime::spawn_blocking(|| -> Result<Vec<db::registry::ListedVault>, String> {
         let settings =
             settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
 
-        let vault_dir = PathBuf::from(&settings.vault_folder_path);
-        std::fs::create_dir_all(&vault_dir)
+        std::fs::create_dir_all(settings.get_vault_folder_path())
             .map_err(|_| "Failed to create vault directory".to_string())?;
 
-        let storages: Vec<String> = std::fs::read_dir(&vault_dir)
-            .map_err(|_| "Failed to read vault directory".to_string())?
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
-                if path.is_file() && path.extension().map_or(false, |ext| ext == "db") {
-                    path.file_name()
-                        .and_then(|s| s.to_str())
-                        .map(|s| s.trim_end_matches(".db").to_string())
-                } else {
-                    None
-                }
-            })
+        vault_registry(&settings)
+            .list(&settings.last_opened, &settings.vault_labels)
+            .map_err(|_| "Failed to read vault directory".to_string())
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+}
+
+/// Хранилища, доступные приложению: основная папка и добавленные места
+fn vault_registry(settings: &settings::AppSettings) -> db::registry::VaultRegistry {
+    db::registry::VaultRegistry::new(
+        settings.get_vault_folder_path(),
+        settings.location_paths(settings::VaultLocationKind::Folder),
+        settings.location_paths(settings::VaultLocationKind::File),
+    )
+}
+
+/// Путь к файлу хранилища `<name>.db` в основной папке
+fn vault_file(settings: &settings::AppSettings, name: &str) -> Result<PathBuf, String> {
+    vault_registry(settings)
+        .folder_vault(name)
+        .map_err(|e| e.to_string())
+}
+
+/// Путь к хранилищу по идентификатору из `populate_list`
+fn resolve_vault(settings: &settings::AppSettings, vault: &str) -> Result<PathBuf, String> {
+    vault_registry(settings)
+        .resolve(vault)
+        .map_err(|e| e.to_string())
+}
+
+/// Добавляет папку с хранилищами или отдельный файл хранилища,
+/// выбранные в системном диалоге. Возвращает `None`, если ничего не выбрано.
+#[tauri::command]
+async fn add_vault_location(
+    app: tauri::AppHandle,
+    kind: settings::VaultLocationKind,
+) -> Result<Option<settings::VaultLocation>, String> {
+    let picker = app.dialog().file();
+    let picked = match kind {
+        settings::VaultLocationKind::Folder => picker.blocking_pick_folder(),
+        settings::VaultLocationKind::File => picker
+            .add_filter("Хранилище", &[db::registry::VAULT_EXTENSION])
+            .blocking_pick_file(),
+    };
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+
+    let path = picked
+        .into_path()
+        .map_err(|_| "Invalid vault path".to_string())?;
+    let path = match kind {
+        settings::VaultLocationKind::Folder => db::registry::canonical_folder(&path),
+        settings::VaultLocationKind::File => db::registry::canonical_vault_file(&path),
+    }
+    .map_err(|e| e.to_string())?;
+    let location = settings::VaultLocation {
+        kind,
+        path: path.to_string_lossy().into_owned(),
+    };
+
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    if !settings.vault_locations.contains(&location) {
+        settings.vault_locations.push(location.clone());
+        settings
+            .save()
+            .map_err(|_| "Failed to save settings".to_string())?;
+    }
+
+    Ok(Some(location))
+}
+
+/// Убирает добавленное место из списка; файлы хранилищ не удаляются
+#[tauri::command]
+async fn remove_vault_location(path: String) -> Result<(), String> {
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+
+    settings
+        .vault_locations
+        .retain(|location| location.path != path);
+
+    settings
+        .save()
+        .map_err(|_| "Failed to save settings".to_string())
+}
+
+/// Закрывает и блокирует сессии, в которых открыт этот файл
+fn close_if_open(state: &AppState, path: &std::path::Path) {
+    let closed: Vec<Arc<Vault>> = {
+        let mut vaults = state.vaults.lock().unwrap();
+        let sessions: Vec<String> = vaults
+            .iter()
+            .filter(|(_, vault)| vault.path() == path)
+            .map(|(session, _)| session.clone())
             .collect();
+        sessions
+            .iter()
+            .filter_map(|session| vaults.remove(session))
+            .collect()
+    };
+
+    lock_closed(closed);
+}
+
+/// Блокирует хранилища закрытых сессий. Хранилище может пережить сессию,
+/// пока его держит выполняющаяся команда, поэтому ключ сбрасывается сразу,
+/// а не при удалении последней ссылки.
+fn lock_closed(vaults: impl IntoIterator<Item = Arc<Vault>>) {
+    for vault in vaults {
+        if let Err(e) = vault.lock() {
+            eprintln!("Failed to lock vault {:?}: {e:#}", vault.path());
+        }
+    }
+}
+
+/// Переименовывает хранилище вместе с его резервными копиями
+#[tauri::command]
+async fn rename_vault(
+    name: String,
+    new_name: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    let from = vault_file(&settings, &name)?;
+    let to = vault_file(&settings, &new_name)?;
+
+    // Занятое имя не должно закрывать открытые сессии
+    db::files::ensure_free(&to).map_err(|e| e.to_string())?;
 
-        Ok(storages)
+    close_if_open(&state, &from);
+
+    db::files::rename(&from, &to).map_err(|e| e.to_string())?;
+
+    // Файл уже переименован: настройки должны указывать на него,
+    // даже если перенести копии не удастся
+    settings.move_vault_records(&from.to_string_lossy(), &to.to_string_lossy());
+    settings
+        .save()
+        .map_err(|_| "Failed to save settings".to_string())?;
+
+    db::backup::rename_all(&settings.get_backup_path(), &from, &to).map_err(|e| e.to_string())
+}
+
+/// Создаёт копию хранилища под новым именем (с тем же мастер-паролем)
+#[tauri::command]
+async fn duplicate_vault(name: String, new_name: String) -> Result<(), String> {
+    let settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    let from = vault_file(&settings, &name)?;
+    let to = vault_file(&settings, &new_name)?;
+
+    tauri::async_runtime::spawn_blocking(move || db::files::duplicate(&from, &to))
+        .await
+        .map_err(|_| "Internal error".to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Удаляет хранилище после подтверждения мастер-паролем:
+/// в корзину ОС или с перезаписью содержимого. Резервные копии сохраняются.
+#[tauri::command]
+async fn delete_vault(
+    name: String,
+    master_password: String,
+    mode: db::files::DeleteMode,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    let path = vault_file(&settings, &name)?;
+    let master_password = Secret::new(Box::new(master_password));
+
+    // Неверный пароль не должен закрывать открытые сессии
+    let checked_path = path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        db::files::check_password(&checked_path, &master_password)
     })
     .await
     .map_err(|_| "Internal error".to_string())?
+    .map_err(|e| e.to_string())?;
+
+    close_if_open(&state, &path);
+
+    let key = path.to_string_lossy().into_owned();
+    tauri::async_runtime::spawn_blocking(move || db::files::delete(&path, mode))
+        .await
+        .map_err(|_| "Internal error".to_string())?
+        .map_err(|e| e.to_string())?;
+
+    settings.forget_vault_records(&key);
+    settings
+        .save()
+        .map_err(|_| "Failed to save settings".to_string())
 }
 
-/// Открыть хранилище
+/// Открыть хранилище по имени в папке хранилищ или по пути добавленного файла.
+/// Возвращает идентификатор сессии; если хранилище уже открыто, после проверки
+/// пароля возвращается его существующая сессия.
 #[tauri::command]
 async fn open_vault(
-    path: String,
+    vault: String,
     master_password: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let vault = db::Vault::new(path);
+) -> Result<String, String> {
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    let vault_path = resolve_vault(&settings, &vault)?;
+
+    let existing = state
+        .vaults
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(_, open)| open.path() == vault_path)
+        .map(|(session, open)| (session.clone(), Arc::clone(open)));
+    if let Some((session, open)) = existing {
+        open.verify_password(&Secret::new(Box::new(master_password)))
+            .map_err(|e| e.to_string())?;
+        return Ok(session);
+    }
+
+    let vault = db::Vault::new(vault_path.clone());
 
     vault
         .unlock(Secret::new(Box::new(master_password)))
         .map_err(|e| e.to_string())?;
 
+    let key = vault_path.to_string_lossy().into_owned();
+    settings
+        .last_opened
+        .insert(key.clone(), db::operations::unix_now());
+    match vault.metadata() {
+        Ok(metadata) => {
+            settings.vault_labels.insert(key, (&metadata).into());
+        }
+        Err(e) => eprintln!("Failed to read vault metadata: {e:#}"),
+    }
+    if let Err(e) = settings.save() {
+        eprintln!("Failed to record last opened vault: {e:#}");
+    }
+
+    if settings.backup.enabled {
+        let policy = backup_policy(&settings);
+        vault.enable_backups(policy.clone());
+        if settings.backup.on_unlock {
+            if let Err(e) = vault.backup_now(&policy) {
+                eprintln!("Backup on unlock failed: {e:#}");
+            }
+        }
+    }
+
     // Сохраняем vault в состоянии
-    *state.vault.lock().unwrap() = Some(vault);
+    let session = new_session_id();
+    state
+        .vaults
+        .lock()
+        .unwrap()
+        .insert(session.clone(), Arc::new(vault));
+
+    Ok(session)
+}
+
+/// Случайный идентификатор сессии (128 бит в шестнадцатеричном виде)
+fn new_session_id() -> String {
+    let bytes: [u8; 16] = rand::random();
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Открытая сессия хранилища
+#[derive(Serialize)]
+struct SessionInfo {
+    session: String,
+    path: String,
+    name: String,
+}
+
+/// Возвращает открытые сессии
+#[tauri::command]
+async fn list_sessions(state: State<'_, AppState>) -> Result<Vec<SessionInfo>, String> {
+    let vaults = state.vaults.lock().unwrap();
 
+    let mut sessions: Vec<SessionInfo> = vaults
+        .iter()
+        .map(|(session, vault)| {
+            let path = vault.path();
+            SessionInfo {
+                session: session.clone(),
+                name: vault_display_name(vault),
+                path: path.to_string_lossy().into_owned(),
+            }
+        })
+        .collect();
+    sessions.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(sessions)
+}
+
+/// Отображаемое имя хранилища или имя файла, если оно не задано
+fn vault_display_name(vault: &Vault) -> String {
+    vault
+        .metadata()
+        .ok()
+        .map(|metadata| metadata.display_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| vault_file_name(vault))
+}
+
+/// Имя файла хранилища без расширения — имя по умолчанию для экспорта
+fn vault_file_name(vault: &Vault) -> String {
+    vault
+        .path()
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Запись, найденная поиском по всем открытым хранилищам
+#[derive(Serialize)]
+struct SearchHit {
+    session: String,
+    vault_name: String,
+    #[serde(flatten)]
+    service: db::operations::EntrySummary,
+}
+
+/// Ищет записи во всех открытых хранилищах по названию, логину и адресу
+#[tauri::command]
+async fn search_entries(
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchHit>, String> {
+    let vaults: Vec<(String, Arc<Vault>)> = state
+        .vaults
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(session, vault)| (session.clone(), Arc::clone(vault)))
+        .collect();
+
+    let mut hits = Vec::new();
+    for (session, vault) in vaults {
+        let vault_name = vault_display_name(&vault);
+        for service in vault.search(&query).map_err(|e| e.to_string())? {
+            hits.push(SearchHit {
+                session: session.clone(),
+                vault_name: vault_name.clone(),
+                service,
+            });
+        }
+    }
+
+    Ok(hits)
+}
+
+/// Возвращает сведения об открытом хранилище
+#[tauri::command]
+async fn get_vault_metadata(
+    session: String,
+    state: State<'_, AppState>,
+) -> Result<db::operations::VaultMetadata, String> {
+    session_vault(&state, &session)?
+        .metadata()
+        .map_err(|e| e.to_string())
+}
+
+/// Изменяет сведения об открытом хранилище и обновляет их копию для списка выбора
+#[tauri::command]
+async fn update_vault_metadata(
+    session: String,
+    metadata: db::operations::VaultMetadataUpdate,
+    state: State<'_, AppState>,
+) -> Result<db::operations::VaultMetadata, String> {
+    let vault = session_vault(&state, &session)?;
+    let updated = vault
+        .update_metadata(&metadata)
+        .map_err(|e| e.to_string())?;
+    let path = vault.path();
+
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    settings
+        .vault_labels
+        .insert(path.to_string_lossy().into_owned(), (&updated).into());
+    settings
+        .save()
+        .map_err(|_| "Failed to save settings".to_string())?;
+
+    Ok(updated)
+}
+
+/// Правила резервного копирования из настроек
+fn backup_policy(settings: &settings::AppSettings) -> db::backup::BackupPolicy {
+    db::backup::BackupPolicy {
+        directory: settings.get_backup_path(),
+        every_changes: settings.backup.every_changes,
+        keep_count: settings.backup.keep_count,
+        max_age_days: settings.backup.max_age_days,
+    }
+}
+
+/// Обновляет настройки резервного копирования
+#[tauri::command]
+async fn update_backup_settings(backup: settings::BackupSettings) -> Result<(), String> {
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+
+    settings.backup = backup;
+
+    settings
+        .save()
+        .map_err(|_| "Failed to save settings".to_string())
+}
+
+/// Обновляет требования к мастер-паролю новых хранилищ
+#[tauri::command]
+async fn update_master_password_settings(
+    master_password: settings::MasterPasswordSettings,
+) -> Result<(), String> {
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+
+    settings.master_password = master_password;
+
+    settings
+        .save()
+        .map_err(|_| "Failed to save settings".to_string())
+}
+
+/// Делает резервную копию открытого хранилища, даже если автоматические копии отключены
+#[tauri::command]
+async fn backup_vault(
+    session: String,
+    state: State<'_, AppState>,
+) -> Result<db::backup::BackupInfo, String> {
+    let settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+
+    session_vault(&state, &session)?
+        .backup_now(&backup_policy(&settings))
+        .map_err(|e| e.to_string())
+}
+
+/// Возвращает резервные копии хранилища, от новых к старым
+#[tauri::command]
+async fn list_backups(vault: String) -> Result<Vec<db::backup::BackupInfo>, String> {
+    let settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    let vault_path = resolve_vault(&settings, &vault)?;
+
+    db::backup::list(&settings.get_backup_path(), &vault_path).map_err(|e| e.to_string())
+}
+
+/// Восстанавливает хранилище из резервной копии после проверки пароля и целостности.
+/// При `dry_run` только сравнивает число записей в копии и в текущем файле.
+/// Если это хранилище открыто, оно закрывается после проверки копии, перед заменой файла.
+#[tauri::command]
+async fn restore_backup(
+    vault: String,
+    backup_path: String,
+    master_password: String,
+    dry_run: bool,
+    state: State<'_, AppState>,
+) -> Result<db::backup::RestoreOutcome, String> {
+    let settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    let vault_path = resolve_vault(&settings, &vault)?;
+    let backup_path = PathBuf::from(backup_path);
+    let master_password = Arc::new(Secret::new(Box::new(master_password)));
+    let directory = settings.get_backup_path();
+
+    // Неверный пароль или чужая либо повреждённая копия не должны закрывать открытые сессии
+    let (checked_directory, checked_backup, checked_vault, checked_password) = (
+        directory.clone(),
+        backup_path.clone(),
+        vault_path.clone(),
+        Arc::clone(&master_password),
+    );
+    let diff = tauri::async_runtime::spawn_blocking(move || {
+        db::backup::inspect(
+            &checked_directory,
+            &checked_backup,
+            &checked_vault,
+            &checked_password,
+        )
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map_err(|e| e.to_string())?;
+
+    if dry_run {
+        return Ok(db::backup::RestoreOutcome::Preview(diff));
+    }
+
+    close_if_open(&state, &vault_path);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        db::backup::restore(&directory, &backup_path, &vault_path, &master_password)
+            .map(db::backup::RestoreOutcome::Restored)
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Закрывает сессию и блокирует её хранилище; остальные сессии остаются открытыми
+#[tauri::command]
+async fn close_vault(session: String, state: State<'_, AppState>) -> Result<(), ()> {
+    let closed = state.vaults.lock().unwrap().remove(&session);
+    lock_closed(closed);
     Ok(())
 }
 
-/// Закрывает сессию и блокирует хранилище
+/// Закрывает все сессии и блокирует их хранилища
 #[tauri::command]
-async fn close_vault(state: State<'_, AppState>) -> Result<(), ()> {
-    *state.vault.lock().unwrap() = None;
+async fn close_all_vaults(state: State<'_, AppState>) -> Result<(), ()> {
+    let closed: Vec<Arc<Vault>> = state
+        .vaults
+        .lock()
+        .unwrap()
+        .drain()
+        .map(|(_, vault)| vault)
+        .collect();
+    lock_closed(closed);
     Ok(())
 }
 
 #[tauri::command]
 async fn list_services(
+    session: String,
     state: State<'_, AppState>,
-) -> Result<Vec<db::operations::ServiceSummary>, String> {
-    let vault = state.vault.lock().unwrap();
-
-    match vault.as_ref() {
-        Some(v) => v.list_services().map_err(|e| e.to_string()),
-        None => Err("Хранилище не открыто".to_string()),
-    }
+) -> Result<Vec<db::operations::EntrySummary>, String> {
+    session_vault(&state, &session)?
+        .list_services()
+        .map_err(|e| e.to_string())
 }
 
 /// Добавляет новую запись
 #[tauri::command]
 async fn add_password(
+    session: String,
     site: String,
     login: String,
     password: String,
     state: State<'_, AppState>,
 ) -> Result<u64, String> {
-    let vault = state.vault.lock().unwrap();
-
-    match vault.as_ref() {
-        Some(v) => v
-            .add_password(&site, &login, &password)
-            .map_err(|e| e.to_string()),
-        None => Err("Хранилище не открыто".to_string()),
-    }
+    session_vault(&state, &session)?
+        .add_password(&site, &login, &password)
+        .map_err(|e| e.to_string())
 }
 
 /// Удаляет запись
 #[tauri::command]
-async fn delete_password(state: State<'_, AppState>, id: u64) -> Result<(), String> {
-    let vault = state.vault.lock().unwrap();
-
-    match vault.as_ref() {
-        Some(v) => v.delete_password(id).map_err(|e| e.to_string()),
-        None => Err("Хранилище не открыто".to_string()),
-    }
+async fn delete_password(
+    session: String,
+    state: State<'_, AppState>,
+    id: u64,
+) -> Result<(), String> {
+    session_vault(&state, &session)?
+        .delete_password(id)
+        .map_err(|e| e.to_string())
 }
 
 /// Получает пароль для записи
 #[tauri::command]
 async fn get_password(
+    session: String,
     state: State<'_, AppState>,
     id: u64,
 ) -> Result<db::operations::PasswordEntry, String> {
-    let vault = state.vault.lock().unwrap();
+    session_vault(&state, &session)?
+        .get_password(id)
+        .map_err(|e| e.to_string())
+}
+
+/// Добавляет заметку
+#[tauri::command]
+async fn add_secure_note(
+    session: String,
+    title: String,
+    body: String,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    session_vault(&state, &session)?
+        .add_secure_note(&title, &body)
+        .map_err(|e| e.to_string())
+}
+
+/// Получает заголовок и текст заметки
+#[tauri::command]
+async fn get_secure_note(
+    session: String,
+    id: u64,
+    state: State<'_, AppState>,
+) -> Result<db::operations::SecureNote, String> {
+    session_vault(&state, &session)?
+        .get_secure_note(id)
+        .map_err(|e| e.to_string())
+}
+
+/// Изменяет заголовок и текст заметки
+#[tauri::command]
+async fn update_secure_note(
+    session: String,
+    id: u64,
+    title: String,
+    body: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    session_vault(&state, &session)?
+        .update_secure_note(id, &title, &body)
+        .map_err(|e| e.to_string())
+}
+
+/// Добавляет платёжную карту. Номер проверяется по алгоритму Луна.
+#[tauri::command]
+async fn add_card(
+    session: String,
+    title: String,
+    details: db::operations::CardDetails,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    session_vault(&state, &session)?
+        .add_card(&title, &details)
+        .map_err(|e| e.to_string())
+}
+
+/// Получает все данные платёжной карты
+#[tauri::command]
+async fn get_card(
+    session: String,
+    id: u64,
+    state: State<'_, AppState>,
+) -> Result<db::operations::Card, String> {
+    session_vault(&state, &session)?
+        .get_card(id)
+        .map_err(|e| e.to_string())
+}
+
+/// Изменяет платёжную карту
+#[tauri::command]
+async fn update_card(
+    session: String,
+    id: u64,
+    title: String,
+    details: db::operations::CardDetails,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    session_vault(&state, &session)?
+        .update_card(id, &title, &details)
+        .map_err(|e| e.to_string())
+}
 
-    match vault.as_ref() {
-        Some(vault) => vault.get_password(id).map_err(|e| e.to_string()),
-        None => Err("Хранилище не открыто".to_string()),
+/// Добавляет личные данные
+#[tauri::command]
+async fn add_identity(
+    session: String,
+    title: String,
+    details: db::operations::IdentityDetails,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    session_vault(&state, &session)?
+        .add_identity(&title, &details)
+        .map_err(|e| e.to_string())
+}
+
+/// Получает все личные данные записи
+#[tauri::command]
+async fn get_identity(
+    session: String,
+    id: u64,
+    state: State<'_, AppState>,
+) -> Result<db::operations::Identity, String> {
+    session_vault(&state, &session)?
+        .get_identity(id)
+        .map_err(|e| e.to_string())
+}
+
+/// Изменяет личные данные
+#[tauri::command]
+async fn update_identity(
+    session: String,
+    id: u64,
+    title: String,
+    details: db::operations::IdentityDetails,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    session_vault(&state, &session)?
+        .update_identity(id, &title, &details)
+        .map_err(|e| e.to_string())
+}
+
+/// Проверяет номер карты при вводе и определяет платёжную систему
+#[tauri::command]
+async fn check_card_number(number: String) -> Result<utils::card::CardNumberCheck, String> {
+    Ok(utils::card::check_number(&number))
+}
+
+/// Создаёт новый ключ SSH Ed25519 и сохраняет его в хранилище
+#[tauri::command]
+async fn generate_ssh_key(
+    session: String,
+    title: String,
+    comment: String,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    let details = utils::ssh_key::generate_ed25519(&comment).map_err(|e| e.to_string())?;
+    session_vault(&state, &session)?
+        .add_ssh_key(&title, &details)
+        .map_err(|e| e.to_string())
+}
+
+/// Добавляет существующий закрытый ключ SSH в формате OpenSSH.
+/// Открытый ключ и отпечаток вычисляются из закрытого ключа.
+#[tauri::command]
+async fn add_ssh_key(
+    session: String,
+    title: String,
+    private_key: String,
+    comment: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<u64, String> {
+    let details = utils::ssh_key::from_private_key(&private_key, comment.as_deref())
+        .map_err(|e| e.to_string())?;
+    session_vault(&state, &session)?
+        .add_ssh_key(&title, &details)
+        .map_err(|e| e.to_string())
+}
+
+/// Получает все данные ключа SSH
+#[tauri::command]
+async fn get_ssh_key(
+    session: String,
+    id: u64,
+    state: State<'_, AppState>,
+) -> Result<db::operations::SshKey, String> {
+    session_vault(&state, &session)?
+        .get_ssh_key(id)
+        .map_err(|e| e.to_string())
+}
+
+/// Запускает встроенный ssh-agent с ключами открытого хранилища.
+/// Агент останавливается при блокировке хранилища. Возвращает путь
+/// к сокету для `SSH_AUTH_SOCK`.
+#[tauri::command]
+async fn start_ssh_agent(
+    app: tauri::AppHandle,
+    session: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    #[cfg(unix)]
+    {
+        let vault = session_vault(&state, &session)?;
+        let settings =
+            settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+        let socket_path = settings.get_ssh_agent_socket_path();
+
+        let confirm: Option<db::ssh_agent::Confirm> = if settings.ssh_agent.confirm_each_use {
+            Some(Arc::new(move |key_use: &db::ssh_agent::KeyUse| {
+                app.dialog()
+                    .message(format!(
+                        "Разрешить использование SSH-ключа «{}»?\n{}",
+                        key_use.title, key_use.fingerprint
+                    ))
+                    .title("SSH-агент")
+                    .kind(MessageDialogKind::Warning)
+                    .buttons(MessageDialogButtons::OkCancel)
+                    .blocking_show()
+            }))
+        } else {
+            None
+        };
+
+        vault
+            .start_ssh_agent(&socket_path, confirm)
+            .map(|path| path.to_string_lossy().into_owned())
+            .map_err(|e| e.to_string())
     }
+    #[cfg(not(unix))]
+    {
+        let _ = (app, session, state);
+        Err("SSH agent is only supported on Unix".to_string())
+    }
+}
+
+/// Останавливает встроенный ssh-agent открытого хранилища
+#[tauri::command]
+async fn stop_ssh_agent(session: String, state: State<'_, AppState>) -> Result<(), String> {
+    let vault = session_vault(&state, &session)?;
+    #[cfg(unix)]
+    vault.stop_ssh_agent();
+    #[cfg(not(unix))]
+    let _ = vault;
+
+    Ok(())
 }
+
+/// Обновляет настройки встроенного ssh-agent. Новые настройки действуют
+/// со следующего запуска агента.
+#[tauri::command]
+async fn update_ssh_agent_settings(ssh_agent: settings::SshAgentSettings) -> Result<(), String> {
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+
+    settings.ssh_agent = ssh_agent;
+
+    settings
+        .save()
+        .map_err(|_| "Failed to save settings".to_string())
+}
+
+/// Копирует записи со всеми полями из одной открытой сессии в другую.
+/// Возвращает ID созданных записей.
+#[tauri::command]
+async fn copy_entries(
+    source: String,
+    destination: String,
+    ids: Vec<u64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<u64>, String> {
+    transfer_entries(&state, &source, &destination, &ids, false)
+}
+
+/// Переносит записи со всеми полями в другую открытую сессию: записи сначала
+/// добавляются в целевое хранилище и только потом удаляются из исходного.
+/// Возвращает ID созданных записей.
+#[tauri::command]
+async fn move_entries(
+    source: String,
+    destination: String,
+    ids: Vec<u64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<u64>, String> {
+    transfer_entries(&state, &source, &destination, &ids, true)
+}
+
+fn transfer_entries(
+    state: &AppState,
+    source: &str,
+    destination: &str,
+    ids: &[u64],
+    remove_source: bool,
+) -> Result<Vec<u64>, String> {
+    if source == destination {
+        return Err("Source and destination vaults must differ".to_string());
+    }
+    let source = session_vault(state, source)?;
+    let destination = session_vault(state, destination)?;
+
+    let mut seen = std::collections::HashSet::new();
+    let ids: Vec<u64> = ids.iter().copied().filter(|id| seen.insert(*id)).collect();
+
+    let records = source.entry_records(&ids).map_err(|e| e.to_string())?;
+    let new_ids = destination
+        .import_records(&records)
+        .map_err(|e| e.to_string())?;
+
+    if remove_source {
+        source.delete_records(&ids).map_err(|e| {
+            format!("Entries were copied, but could not be removed from the source vault: {e}")
+        })?;
+    }
+
+    Ok(new_ids)
+}
+
+/// Возвращает вложения записи без содержимого
+#[tauri::command]
+async fn list_attachments(
+    session: String,
+    entry_id: u64,
+    state: State<'_, AppState>,
+) -> Result<Vec<db::attachments::AttachmentInfo>, String> {
+    session_vault(&state, &session)?
+        .attachments(entry_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Прикрепляет к записи файл, выбранный в системном диалоге.
+/// Возвращает `None`, если файл не выбран.
+#[tauri::command]
+async fn attach_file(
+    app: tauri::AppHandle,
+    session: String,
+    entry_id: u64,
+    state: State<'_, AppState>,
+) -> Result<Option<db::attachments::AttachmentInfo>, String> {
+    let vault = session_vault(&state, &session)?;
+
+    let Some(file) = app.dialog().file().blocking_pick_file() else {
+        return Ok(None);
+    };
+    let path = file
+        .into_path()
+        .map_err(|_| "Invalid attachment path".to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || vault.attach_file(entry_id, &path))
+        .await
+        .map_err(|_| "Internal error".to_string())?
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Сохраняет вложение в файл, выбранный в системном диалоге.
+/// Возвращает путь или `None`, если пользователь отменил сохранение.
+#[tauri::command]
+async fn export_attachment(
+    app: tauri::AppHandle,
+    session: String,
+    attachment_id: u64,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let vault = session_vault(&state, &session)?;
+    let attachment = vault.attachment(attachment_id).map_err(|e| e.to_string())?;
+
+    let Some(file) = app
+        .dialog()
+        .file()
+        .set_file_name(&attachment.name)
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .into_path()
+        .map_err(|_| "Invalid export path".to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || -> anyhow::Result<String> {
+        vault.export_attachment(attachment_id, &path)?;
+        Ok(path.to_string_lossy().into_owned())
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map(Some)
+    .map_err(|e| e.to_string())
+}
+
+/// Удаляет вложение
+#[tauri::command]
+async fn delete_attachment(
+    session: String,
+    attachment_id: u64,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    session_vault(&state, &session)?
+        .delete_attachment(attachment_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Срок, после которого пароль считается старым, если он не указан
+const DEFAULT_MAX_PASSWORD_AGE_DAYS: u32 = 365;
+
+/// Проверяет пароли открытого хранилища: слабые, повторяющиеся, старые,
+/// записи без TOTP и адреса без HTTPS. Возвращает только ID записей и общую оценку.
+#[tauri::command]
+async fn vault_health_report(
+    session: String,
+    max_age_days: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<audit::health::HealthReport, String> {
+    let vault = session_vault(&state, &session)?;
+    let max_age_days = max_age_days.unwrap_or(DEFAULT_MAX_PASSWORD_AGE_DAYS);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let records = vault.identified_records()?;
+        Ok::<_, anyhow::Error>(audit::health::report(
+            &records,
+            max_age_days,
+            db::operations::unix_now(),
+        ))
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Выбирает локальный список утечек Pwned Passwords в системном диалоге
+/// и сохраняет его в настройках: общий файл или, при `folder`, папку файлов
+/// диапазонов. Возвращает `None`, если ничего не выбрано.
+#[tauri::command]
+async fn pick_breach_hash_file(
+    folder: bool,
+    app: tauri::AppHandle,
+) -> Result<Option<String>, String> {
+    let dialog = app.dialog().file();
+    let picked = if folder {
+        dialog.blocking_pick_folder()
+    } else {
+        dialog
+            .add_filter("Pwned Passwords SHA-1", &["txt"])
+            .blocking_pick_file()
+    };
+    let Some(file) = picked else {
+        return Ok(None);
+    };
+
+    let path = file
+        .into_path()
+        .map_err(|_| "Invalid hash list path".to_string())?;
+    let check_path = path.clone();
+    tauri::async_runtime::spawn_blocking(move || audit::breach::HashList::open(&check_path))
+        .await
+        .map_err(|_| "Internal error".to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let path = path.to_string_lossy().into_owned();
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    settings.breach_check.hash_file = path.clone();
+    settings
+        .save()
+        .map_err(|_| "Failed to save settings".to_string())?;
+
+    Ok(Some(path))
+}
+
+/// Проверяет пароли открытого хранилища по локальному списку утечек
+/// без обращения к сети. Возвращает ID записей и число утечек для каждой.
+#[tauri::command]
+async fn check_breaches_offline(
+    session: String,
+    state: State<'_, AppState>,
+) -> Result<audit::breach::BreachReport, String> {
+    let vault = session_vault(&state, &session)?;
+    let settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    if settings.breach_check.hash_file.is_empty() {
+        return Err("Breach hash list is not selected".to_string());
+    }
+    let hash_file = PathBuf::from(settings.breach_check.hash_file);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut list = audit::breach::HashList::open(&hash_file)?;
+        let records = vault.identified_records()?;
+        audit::breach::check(&records, &mut list)
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Обновляет настройки проверки утечек. Адрес сервиса проверяется,
+/// а при отключении проверки по сети сохранённые ответы забываются.
+#[tauri::command]
+async fn update_breach_settings(
+    mut breach_check: settings::BreachCheckSettings,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    breach_check.range_api_url = audit::range_api::normalize_base_url(&breach_check.range_api_url)
+        .map_err(|e| e.to_string())?;
+
+    let mut settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    if !breach_check.online_enabled {
+        state.breach_cache.lock().unwrap().clear();
+    }
+    settings.breach_check = breach_check;
+
+    settings
+        .save()
+        .map_err(|_| "Failed to save settings".to_string())
+}
+
+/// Проверяет пароли открытого хранилища по сервису утечек с k-анонимностью:
+/// в сеть уходят только первые пять символов SHA-1 каждого пароля.
+/// Работает, только если пользователь явно включил проверку по сети.
+#[tauri::command]
+async fn check_breaches_online(
+    session: String,
+    state: State<'_, AppState>,
+) -> Result<audit::breach::BreachReport, String> {
+    let vault = session_vault(&state, &session)?;
+    let settings =
+        settings::AppSettings::load().map_err(|_| "Failed to load settings".to_string())?;
+    let breach_check = settings.breach_check;
+    if !breach_check.online_enabled {
+        return Err("Online breach check is disabled in settings".to_string());
+    }
+
+    let ttl = std::time::Duration::from_secs(u64::from(breach_check.cache_hours) * 60 * 60);
+    let cache = Arc::clone(&state.breach_cache);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut api = audit::range_api::RangeApi::new(
+            &breach_check.range_api_url,
+            breach_check.padding,
+            ttl,
+            cache,
+        )?;
+        let records = vault.identified_records()?;
+        audit::breach::check(&records, &mut api)
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Экспортирует открытое хранилище в зашифрованный JSON-файл.
+/// Возвращает путь к файлу или `None`, если пользователь отменил сохранение.
+#[tauri::command]
+async fn export_vault(
+    app: tauri::AppHandle,
+    session: String,
+    export_password: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    if export_password.is_empty() {
+        return Err("Export password must not be empty".to_string());
+    }
+    let export_password = Secret::new(Box::new(export_password));
+
+    let payload = {
+        let v = session_vault(&state, &session)?;
+        let vault_name = vault_file_name(&v);
+        let entries = v.export_records().map_err(|e| e.to_string())?;
+        export::encrypted::ExportPayload::new(vault_name, entries)
+    };
+
+    let Some(file) = app
+        .dialog()
+        .file()
+        .set_file_name(format!("{}.json", payload.vault_name))
+        .add_filter("NoPeekPanda export", &["json"])
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .into_path()
+        .map_err(|_| "Invalid export path".to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || -> anyhow::Result<String> {
+        let contents = export::encrypted::encrypt(&payload, &export_password)?;
+        utils::fs::create_private_file(&path)?
+            .write_all(contents.as_bytes())
+            .map_err(|e| anyhow!("Failed to write export file: {}", e))?;
+        Ok(path.to_string_lossy().into_owned())
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map(Some)
+    .map_err(|e| e.to_string())
+}
+
+/// Импортирует записи из зашифрованного JSON-экспорта в открытое хранилище.
+/// Возвращает число добавленных записей или `None`, если файл не выбран.
+#[tauri::command]
+async fn import_vault(
+    app: tauri::AppHandle,
+    session: String,
+    export_password: String,
+    state: State<'_, AppState>,
+) -> Result<Option<usize>, String> {
+    let export_password = Secret::new(Box::new(export_password));
+    let vault = session_vault(&state, &session)?;
+
+    let Some(file) = app
+        .dialog()
+        .file()
+        .add_filter("NoPeekPanda export", &["json"])
+        .blocking_pick_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .into_path()
+        .map_err(|_| "Invalid import path".to_string())?;
+
+    let payload = tauri::async_runtime::spawn_blocking(move || {
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read export file: {}", e))?;
+        export::encrypted::decrypt(&contents, &export_password)
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map_err(|e| e.to_string())?;
+
+    vault
+        .import_records(&payload.entries)
+        .map(|ids| Some(ids.len()))
+        .map_err(|e| e.to_string())
+}
+
+/// Экспортирует открытое хранилище в файл KeePass KDBX 4 (Argon2id + ChaCha20),
+/// который открывается в KeePassXC. Возвращает путь или `None` при отмене.
+#[tauri::command]
+async fn export_kdbx(
+    app: tauri::AppHandle,
+    session: String,
+    export_password: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    if export_password.is_empty() {
+        return Err("Export password must not be empty".to_string());
+    }
+    let export_password = Secret::new(Box::new(export_password));
+
+    let (vault_name, entries) = {
+        let v = session_vault(&state, &session)?;
+        let vault_name = vault_file_name(&v);
+        let entries = v.export_records().map_err(|e| e.to_string())?;
+        (vault_name, entries)
+    };
+
+    let Some(file) = app
+        .dialog()
+        .file()
+        .set_file_name(format!("{}.kdbx", vault_name))
+        .add_filter("KeePass", &["kdbx"])
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .into_path()
+        .map_err(|_| "Invalid export path".to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || -> anyhow::Result<String> {
+        let mut file = utils::fs::create_private_file(&path)?;
+        export::kdbx::write(&vault_name, &entries, &export_password, &mut file)?;
+        Ok(path.to_string_lossy().into_owned())
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map(Some)
+    .map_err(|e| e.to_string())
+}
+
+/// Экспортирует открытое хранилище в НЕЗАШИФРОВАННЫЙ CSV.
+/// Требует повторного ввода мастер-пароля и явного подтверждения;
+/// возвращает путь к файлу или `None`, если пользователь отказался.
+#[tauri::command]
+async fn export_csv(
+    app: tauri::AppHandle,
+    session: String,
+    master_password: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let master_password = Secret::new(Box::new(master_password));
+
+    let (vault_name, records) = {
+        let v = session_vault(&state, &session)?;
+        v.verify_password(&master_password)
+            .map_err(|_| "Неверный мастер-пароль".to_string())?;
+        let vault_name = vault_file_name(&v);
+        (vault_name, v.export_records().map_err(|e| e.to_string())?)
+    };
+
+    let confirmed = app
+        .dialog()
+        .message("Все пароли будут сохранены в файл в открытом виде. Продолжить?")
+        .title("Экспорт в CSV")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancel)
+        .blocking_show();
+    if !confirmed {
+        return Ok(None);
+    }
+
+    let Some(file) = app
+        .dialog()
+        .file()
+        .set_file_name(format!("{vault_name}.csv"))
+        .add_filter("CSV", &["csv"])
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .into_path()
+        .map_err(|_| "Invalid export path".to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || -> anyhow::Result<String> {
+        export::csv::write_csv(&path, &records)?;
+        Ok(path.to_string_lossy().into_owned())
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map(Some)
+    .map_err(|e| e.to_string())
+}
+
+/// Открывает диалог выбора файла для импорта в указанном формате.
+/// Возвращает путь или `None`, если файл не выбран.
+#[tauri::command]
+async fn pick_import_file(
+    app: tauri::AppHandle,
+    source: import::ImportSource,
+) -> Result<Option<String>, String> {
+    let (filter_name, extensions) = source.file_filter();
+    let Some(file) = app
+        .dialog()
+        .file()
+        .add_filter(filter_name, extensions)
+        .blocking_pick_file()
+    else {
+        return Ok(None);
+    };
+
+    file.into_path()
+        .map(|path| Some(path.to_string_lossy().into_owned()))
+        .map_err(|_| "Invalid import path".to_string())
+}
+
+/// Импортирует записи из файла другого менеджера паролей в открытое хранилище.
+/// При `dry_run` ничего не записывает и возвращает предпросмотр с решениями по дубликатам.
+#[tauri::command]
+async fn import_entries(
+    session: String,
+    path: String,
+    source: import::ImportSource,
+    dry_run: bool,
+    decisions: Option<Vec<import::ConflictDecision>>,
+    state: State<'_, AppState>,
+) -> Result<import::ImportOutcome, String> {
+    let parsed = tauri::async_runtime::spawn_blocking(move || {
+        import::parse_file(source, &PathBuf::from(path))
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let vault = session_vault(&state, &session)?;
+    finish_import(&vault, parsed, dry_run, decisions.unwrap_or_default())
+}
+
+/// Импортирует записи из файла KeePass (KDBX 3.1/4) в открытое хранилище.
+/// При `dry_run` ничего не записывает и возвращает предпросмотр с решениями по дубликатам.
+#[tauri::command]
+async fn import_keepass(
+    session: String,
+    path: String,
+    password: String,
+    key_file: Option<String>,
+    dry_run: bool,
+    decisions: Option<Vec<import::ConflictDecision>>,
+    state: State<'_, AppState>,
+) -> Result<import::ImportOutcome, String> {
+    let password = Secret::new(Box::new(password));
+
+    let parsed = tauri::async_runtime::spawn_blocking(move || {
+        let key_file = key_file.map(PathBuf::from);
+        let database = import::keepass::open(&PathBuf::from(path), &password, key_file.as_deref())?;
+        Ok::<_, anyhow::Error>(import::keepass::parse(&database))
+    })
+    .await
+    .map_err(|_| "Internal error".to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let vault = session_vault(&state, &session)?;
+    finish_import(&vault, parsed, dry_run, decisions.unwrap_or_default())
+}
+
+/// Общий конец импорта: предпросмотр либо запись с учётом решений по дубликатам
+fn finish_import(
+    vault: &Vault,
+    parsed: import::ParsedImport,
+    dry_run: bool,
+    decisions: Vec<import::ConflictDecision>,
+) -> Result<import::ImportOutcome, String> {
+    if dry_run {
+        import::preview(vault, &parsed).map(import::ImportOutcome::Preview)
+    } else {
+        import::commit(vault, parsed, &decisions).map(import::ImportOutcome::Imported)
+    }
+    .map_err(|e| e.to_string())
+}
+
 /// Проверяет существование директории
 // #[tauri::command]
 // async fn check_directory_exists(path: String) -> Result<bool, String> {
@@ -246,6 +1567,14 @@ async fn install_update(app: tauri::AppHandle) -> Result<(), String> {
     }
 }
 
+/// Возвращает состояние защиты памяти процесса (core dump, mlock)
+#[tauri::command]
+async fn memory_protection_status(
+    state: State<'_, AppState>,
+) -> Result<memory::ProtectionStatus, String> {
+    Ok(state.protection.clone())
+}
+
 /// Возвращает тип операционной системы («windows», «linux», «macos»)
 #[tauri::command]
 async fn get_os() -> Result<String, String> {
@@ -254,10 +1583,18 @@ async fn get_os() -> Result<String, String> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Запрещаем core dump и проверяем mlock до того, как в памяти появятся секреты
+    let protection = memory::harden_process();
+    for warning in &protection.warnings {
+        eprintln!("{warning}");
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_updater::Builder::new().build())
         .manage(AppState {
-            vault: Arc::new(Mutex::new(None)),
+            vaults: Arc::new(Mutex::new(HashMap::new())),
+            protection,
+            breach_cache: Arc::new(Mutex::new(audit::range_api::RangeCache::default())),
         })
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
@@ -265,16 +1602,67 @@ pub fn run() {
             get_settings,
             update_settings,
             create_vault,
+            estimate_password_strength,
             populate_list,
             open_vault,
             close_vault,
+            close_all_vaults,
+            list_sessions,
+            search_entries,
+            get_vault_metadata,
+            update_vault_metadata,
+            rename_vault,
+            duplicate_vault,
+            delete_vault,
+            add_vault_location,
+            remove_vault_location,
+            update_backup_settings,
+            update_master_password_settings,
+            backup_vault,
+            list_backups,
+            restore_backup,
             list_services,
             check_update,
             install_update,
             get_os,
+            memory_protection_status,
             get_password,
             add_password,
-            delete_password
+            delete_password,
+            add_secure_note,
+            get_secure_note,
+            update_secure_note,
+            add_card,
+            get_card,
+            update_card,
+            check_card_number,
+            add_identity,
+            get_identity,
+            update_identity,
+            generate_ssh_key,
+            add_ssh_key,
+            get_ssh_key,
+            start_ssh_agent,
+            stop_ssh_agent,
+            update_ssh_agent_settings,
+            copy_entries,
+            move_entries,
+            list_attachments,
+            attach_file,
+            export_attachment,
+            delete_attachment,
+            vault_health_report,
+            pick_breach_hash_file,
+            check_breaches_offline,
+            update_breach_settings,
+            check_breaches_online,
+            export_vault,
+            import_vault,
+            export_kdbx,
+            export_csv,
+            pick_import_file,
+            import_entries,
+            import_keepass
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
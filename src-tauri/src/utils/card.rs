@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+
+/// Допустимая длина номера карты в цифрах
+const NUMBER_LEN: std::ops::RangeInclusive<usize> = 12..=19;
+
+/// Платёжная система карты
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardBrand {
+    Visa,
+    Mastercard,
+    Amex,
+    Discover,
+    Jcb,
+    DinersClub,
+    UnionPay,
+    Maestro,
+    Mir,
+}
+
+/// Результат проверки номера карты
+#[derive(Debug, Serialize)]
+pub struct CardNumberCheck {
+    /// Номер из 12–19 цифр, прошедший проверку Луна
+    pub valid: bool,
+    pub brand: Option<CardBrand>,
+}
+
+/// Номер без пробелов и дефисов; `None`, если в нём есть другие символы
+pub fn normalize_number(number: &str) -> Option<String> {
+    let digits: String = number
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    digits.chars().all(|c| c.is_ascii_digit()).then_some(digits)
+}
+
+/// Проверяет номер карты и определяет платёжную систему
+pub fn check_number(number: &str) -> CardNumberCheck {
+    let Some(digits) = normalize_number(number) else {
+        return CardNumberCheck {
+            valid: false,
+            brand: None,
+        };
+    };
+
+    CardNumberCheck {
+        valid: NUMBER_LEN.contains(&digits.len()) && luhn_valid(&digits),
+        brand: detect_brand(&digits),
+    }
+}
+
+/// Контрольная сумма по алгоритму Луна; `digits` — только цифры
+pub fn luhn_valid(digits: &str) -> bool {
+    let mut sum = 0;
+    for (index, c) in digits.bytes().rev().enumerate() {
+        let mut digit = u32::from(c - b'0');
+        if index % 2 == 1 {
+            digit *= 2;
+            if digit > 9 {
+                digit -= 9;
+            }
+        }
+        sum += digit;
+    }
+
+    !digits.is_empty() && sum % 10 == 0
+}
+
+/// Платёжная система по начальным цифрам номера (IIN)
+pub fn detect_brand(digits: &str) -> Option<CardBrand> {
+    let prefix = |len| leading_number(digits, len);
+
+    let brand = match (prefix(1), prefix(2), prefix(3), prefix(4), prefix(6)) {
+        (_, _, _, 2200..=2204, _) => CardBrand::Mir,
+        (4, _, _, _, _) => CardBrand::Visa,
+        (_, 51..=55, _, _, _) | (_, _, _, 2221..=2720, _) => CardBrand::Mastercard,
+        (_, 34 | 37, _, _, _) => CardBrand::Amex,
+        (_, _, _, 6011, _) | (_, 65, _, _, _) | (_, _, 644..=649, _, _) => CardBrand::Discover,
+        (_, _, _, _, 622126..=622925) => CardBrand::Discover,
+        (_, _, _, 3528..=3589, _) => CardBrand::Jcb,
+        (_, 36 | 38 | 39, _, _, _) | (_, _, 300..=305, _, _) => CardBrand::DinersClub,
+        (_, 62, _, _, _) => CardBrand::UnionPay,
+        (_, 50 | 56..=69, _, _, _) => CardBrand::Maestro,
+        _ => return None,
+    };
+
+    Some(brand)
+}
+
+/// Маскирует значение, оставляя последние четыре символа: «•••• 1234»
+pub fn mask_tail(value: &str) -> String {
+    let chars: Vec<char> = value.chars().filter(|c| !c.is_whitespace()).collect();
+    // Короткое значение целиком совпало бы со своим «хвостом»
+    if chars.len() <= 4 {
+        return "••••".to_string();
+    }
+    let tail: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+    format!("•••• {tail}")
+}
+
+/// Первые `len` цифр номера как число; 0, если номер короче
+fn leading_number(digits: &str, len: usize) -> u32 {
+    digits
+        .get(..len)
+        .and_then(|prefix| prefix.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn luhn_accepts_test_numbers() {
+        for number in [
+            "4111111111111111",
+            "5555555555554444",
+            "378282246310005",
+            "6011111111111117",
+            "3530111333300000",
+            "30569309025904",
+            "0",
+        ] {
+            assert!(luhn_valid(number), "{number}");
+        }
+    }
+
+    #[test]
+    fn luhn_rejects_typos() {
+        for number in ["4111111111111112", "4111111111111121", "1", ""] {
+            assert!(!luhn_valid(number), "{number}");
+        }
+    }
+
+    #[test]
+    fn number_check_ignores_separators() {
+        let check = check_number("4111 1111-1111 1111");
+        assert!(check.valid);
+        assert_eq!(check.brand, Some(CardBrand::Visa));
+
+        // Проходит Луна, но слишком короткий
+        assert!(!check_number("0000 0000").valid);
+        assert!(!check_number(&"0".repeat(20)).valid);
+
+        let check = check_number("4111 1111 1111 111x");
+        assert!(!check.valid);
+        assert_eq!(check.brand, None);
+    }
+
+    #[test]
+    fn brand_is_detected_by_prefix() {
+        let cases = [
+            ("4111111111111111", Some(CardBrand::Visa)),
+            ("5555555555554444", Some(CardBrand::Mastercard)),
+            ("2223003122003222", Some(CardBrand::Mastercard)),
+            ("378282246310005", Some(CardBrand::Amex)),
+            ("6011111111111117", Some(CardBrand::Discover)),
+            ("6445644564456445", Some(CardBrand::Discover)),
+            ("6221260000000000", Some(CardBrand::Discover)),
+            ("3530111333300000", Some(CardBrand::Jcb)),
+            ("30569309025904", Some(CardBrand::DinersClub)),
+            ("36227206271667", Some(CardBrand::DinersClub)),
+            ("6200000000000005", Some(CardBrand::UnionPay)),
+            ("6759649826438453", Some(CardBrand::Maestro)),
+            ("2200123456789010", Some(CardBrand::Mir)),
+            ("1234567890123456", None),
+            ("", None),
+        ];
+
+        for (number, brand) in cases {
+            assert_eq!(detect_brand(number), brand, "{number}");
+        }
+    }
+
+    #[test]
+    fn mask_keeps_last_four_characters() {
+        assert_eq!(mask_tail("4111111111111111"), "•••• 1111");
+        assert_eq!(mask_tail("4510 123456"), "•••• 3456");
+        assert_eq!(mask_tail("AB-7Ж"), "•••• B-7Ж");
+    }
+
+    #[test]
+    fn short_values_are_masked_entirely() {
+        assert_eq!(mask_tail("1234"), "••••");
+        assert_eq!(mask_tail(" 12 3 "), "••••");
+        assert_eq!(mask_tail(""), "••••");
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+/// Создаёт (или перезаписывает) файл, доступный только владельцу (0600 на Unix)
+pub fn create_private_file<P: AsRef<Path>>(path: P) -> Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    open_private(path.as_ref(), options)
+}
+
+/// Создаёт файл, доступный только владельцу; существующий файл не трогает
+pub fn create_new_private_file<P: AsRef<Path>>(path: P) -> Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    open_private(path.as_ref(), options)
+}
+
+fn open_private(path: &Path, mut options: OpenOptions) -> Result<File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let file = options
+        .open(path)
+        .with_context(|| format!("Failed to create file: {:?}", path))?;
+
+    // mode() действует только при создании — у существующего файла права сужаем явно
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to restrict permissions: {:?}", path))?;
+    }
+
+    Ok(file)
+}
+
+/// Создаёт каталог, доступный только владельцу (0700). Существующий каталог
+/// должен принадлежать текущему пользователю и быть закрыт для остальных.
+#[cfg(unix)]
+pub fn create_private_dir<P: AsRef<Path>>(path: P) -> Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    let path = path.as_ref();
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(path)
+        .with_context(|| format!("Failed to create directory: {:?}", path))?;
+
+    // Каталог мог существовать заранее, например созданный другим пользователем в /tmp
+    let metadata = std::fs::symlink_metadata(path)
+        .with_context(|| format!("Failed to read directory metadata: {:?}", path))?;
+    if !metadata.is_dir() || metadata.uid() != current_uid() || metadata.mode() & 0o077 != 0 {
+        anyhow::bail!(
+            "Directory must be owned by the current user and closed to others: {:?}",
+            path
+        );
+    }
+
+    Ok(())
+}
+
+/// Действующий uid процесса
+#[cfg(unix)]
+pub fn current_uid() -> u32 {
+    // SAFETY: geteuid не принимает аргументов и всегда успешен
+    unsafe { libc::geteuid() }
+}
+
+/// Временная папка теста; удаляется вместе с содержимым
+#[cfg(test)]
+pub(crate) struct TestDir(std::path::PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("nopeekpanda-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub(crate) fn join<P: AsRef<Path>>(&self, path: P) -> std::path::PathBuf {
+        self.0.join(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
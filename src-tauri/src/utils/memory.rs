@@ -0,0 +1,224 @@
+use serde::Serialize;
+use std::alloc::Layout;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+use zeroize::Zeroize;
+
+/// Удалось ли заблокировать тестовую страницу при старте (mlock доступен)
+static MLOCK_AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// Состояние защиты памяти процесса
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtectionStatus {
+    /// Core dump отключены (RLIMIT_CORE = 0, на Linux ещё и PR_SET_DUMPABLE = 0)
+    pub core_dumps_disabled: bool,
+    /// Страницы с секретами удерживаются в RAM через mlock
+    pub memory_locking: bool,
+    /// Причины, по которым часть защиты не включилась
+    pub warnings: Vec<String>,
+}
+
+/// Включает защиту процесса: запрещает core dump и проверяет доступность mlock.
+///
+/// Никогда не завершается ошибкой — всё, что не удалось включить, попадает в `warnings`.
+pub fn harden_process() -> ProtectionStatus {
+    let mut warnings = Vec::new();
+
+    let core_dumps_disabled = match disable_core_dumps() {
+        Ok(()) => true,
+        Err(e) => {
+            warnings.push(format!("Core dumps are not disabled: {e}"));
+            false
+        }
+    };
+
+    let memory_locking = match probe_mlock() {
+        Ok(()) => true,
+        Err(e) => {
+            warnings.push(format!("Memory locking is unavailable: {e}"));
+            false
+        }
+    };
+    MLOCK_AVAILABLE.store(memory_locking, Ordering::Relaxed);
+
+    ProtectionStatus {
+        core_dumps_disabled,
+        memory_locking,
+        warnings,
+    }
+}
+
+/// Копия секрета в собственных страницах памяти, закреплённых в RAM.
+///
+/// mlock работает со страницами целиком и не считает вызовы, поэтому
+/// блокировать чужой буфер нельзя: munlock снял бы блокировку и с соседних
+/// данных на тех же страницах. Здесь страницы принадлежат только буферу,
+/// а при уничтожении содержимое затирается.
+pub struct LockedBuffer {
+    ptr: NonNull<u8>,
+    len: usize,
+    layout: Layout,
+    locked: bool,
+}
+
+// SAFETY: буфер владеет своей памятью так же, как Box<[u8]>
+unsafe impl Send for LockedBuffer {}
+// SAFETY: через &LockedBuffer память только читается
+unsafe impl Sync for LockedBuffer {}
+
+/// Копирует секрет в буфер, закреплённый в RAM, чтобы он не попал в swap.
+///
+/// Если mlock недоступен или не сработал, буфер остаётся обычным —
+/// секрет используется как прежде и всё равно затирается при уничтожении.
+///
+/// Строку пароля, пришедшую из интерфейса, закрепить нельзя (её память делит
+/// страницы с другими данными); `SecretBox` затирает её при уничтожении,
+/// а ключи выводятся только из закреплённой копии.
+pub fn locked_copy(bytes: &[u8]) -> LockedBuffer {
+    let page_size = sys::page_size();
+    let size = bytes.len().max(1).div_ceil(page_size) * page_size;
+    let layout = Layout::from_size_align(size, page_size).expect("Invalid locked buffer layout");
+
+    // SAFETY: размер layout ненулевой
+    let ptr = NonNull::new(unsafe { std::alloc::alloc_zeroed(layout) })
+        .unwrap_or_else(|| std::alloc::handle_alloc_error(layout));
+
+    // Страницы блокируются до копирования, чтобы секрет сразу лежал в закреплённой памяти
+    let locked = MLOCK_AVAILABLE.load(Ordering::Relaxed) && sys::mlock(ptr.as_ptr(), size).is_ok();
+
+    // SAFETY: буфер только что выделен, вмещает bytes.len() байт и не пересекается с bytes
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.as_ptr(), bytes.len()) };
+
+    LockedBuffer {
+        ptr,
+        len: bytes.len(),
+        layout,
+        locked,
+    }
+}
+
+impl LockedBuffer {
+    /// Закреплена ли память буфера в RAM
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
+
+impl std::ops::Deref for LockedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: первые len байт выделенной памяти инициализированы копией секрета
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for LockedBuffer {
+    fn drop(&mut self) {
+        // SAFETY: вся выделенная память инициализирована (alloc_zeroed) и принадлежит буферу
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.layout.size()) }.zeroize();
+
+        if self.locked {
+            sys::munlock(self.ptr.as_ptr(), self.layout.size());
+        }
+
+        // SAFETY: память выделена с этим же layout и больше не используется
+        unsafe { std::alloc::dealloc(self.ptr.as_ptr(), self.layout) };
+    }
+}
+
+fn disable_core_dumps() -> Result<(), String> {
+    sys::disable_core_dumps()
+}
+
+fn probe_mlock() -> Result<(), String> {
+    let probe = vec![0u8; 4096];
+    sys::mlock(probe.as_ptr(), probe.len())?;
+    sys::munlock(probe.as_ptr(), probe.len());
+    Ok(())
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::io;
+
+    pub fn disable_core_dumps() -> Result<(), String> {
+        let limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: передаём указатель на корректно инициализированную структуру
+        if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } != 0 {
+            return Err(format!("setrlimit: {}", io::Error::last_os_error()));
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        // SAFETY: PR_SET_DUMPABLE принимает одно целочисленное значение
+        if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } != 0 {
+            return Err(format!("prctl: {}", io::Error::last_os_error()));
+        }
+
+        Ok(())
+    }
+
+    pub fn mlock(ptr: *const u8, len: usize) -> Result<(), String> {
+        // SAFETY: ptr/len описывают живой буфер вызывающей стороны
+        if unsafe { libc::mlock(ptr.cast(), len) } != 0 {
+            return Err(format!("mlock: {}", io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    pub fn munlock(ptr: *const u8, len: usize) {
+        // SAFETY: munlock не обращается к содержимому памяти
+        unsafe {
+            libc::munlock(ptr.cast(), len);
+        }
+    }
+
+    pub fn page_size() -> usize {
+        // SAFETY: sysconf не принимает указателей
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        usize::try_from(size)
+            .ok()
+            .filter(|size| size.is_power_of_two())
+            .unwrap_or(4096)
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    pub fn disable_core_dumps() -> Result<(), String> {
+        Err("not supported on this platform".to_string())
+    }
+
+    pub fn mlock(_ptr: *const u8, _len: usize) -> Result<(), String> {
+        Err("not supported on this platform".to_string())
+    }
+
+    pub fn munlock(_ptr: *const u8, _len: usize) {}
+
+    pub fn page_size() -> usize {
+        4096
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locked_copy_owns_whole_pages() {
+        let secret = b"correct horse battery staple";
+        let buffer = locked_copy(secret);
+
+        assert_eq!(&*buffer, secret);
+        assert_eq!(buffer.ptr.as_ptr() as usize % sys::page_size(), 0);
+        assert_eq!(buffer.layout.size() % sys::page_size(), 0);
+    }
+
+    #[test]
+    fn empty_secret_gets_a_buffer() {
+        assert!(locked_copy(&[]).is_empty());
+    }
+}
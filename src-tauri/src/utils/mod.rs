@@ -1 +1,6 @@
+pub mod card;
+pub mod fs;
+pub mod memory;
 pub mod settings;
+pub mod ssh_key;
+pub mod strength;
@@ -1,13 +1,28 @@
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+/// Сервис Pwned Passwords по умолчанию
+pub const DEFAULT_RANGE_API_URL: &str = "https://api.pwnedpasswords.com";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct AppSettings {
     pub vault_folder_path: String,
     pub theme: String, // "latte", "frappe", "macchiato", "mocha"
+    pub backup: BackupSettings,
+    pub master_password: MasterPasswordSettings,
+    /// Дополнительные места хранилищ вне основной папки
+    pub vault_locations: Vec<VaultLocation>,
+    /// Время последнего открытия хранилищ (секунды Unix) по пути к файлу
+    pub last_opened: HashMap<String, i64>,
+    /// Несекретные сведения о хранилищах для списка выбора по пути к файлу
+    pub vault_labels: HashMap<String, VaultLabel>,
+    pub breach_check: BreachCheckSettings,
+    pub ssh_agent: SshAgentSettings,
 }
 
 impl Default for AppSettings {
@@ -15,6 +30,144 @@ impl Default for AppSettings {
         Self {
             vault_folder_path: String::new(),
             theme: "mocha".to_string(),
+            backup: BackupSettings::default(),
+            master_password: MasterPasswordSettings::default(),
+            vault_locations: Vec::new(),
+            last_opened: HashMap::new(),
+            vault_labels: HashMap::new(),
+            breach_check: BreachCheckSettings::default(),
+            ssh_agent: SshAgentSettings::default(),
+        }
+    }
+}
+
+/// Требования к мастер-паролю новых хранилищ
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct MasterPasswordSettings {
+    /// Пароль с оценкой стойкости (0–4) ниже этой считается слабым
+    pub min_score: u8,
+    /// Запрещать создание хранилища со слабым паролем, а не только предупреждать
+    pub block_weak: bool,
+}
+
+impl Default for MasterPasswordSettings {
+    fn default() -> Self {
+        Self {
+            min_score: 3,
+            block_weak: false,
+        }
+    }
+}
+
+impl MasterPasswordSettings {
+    pub fn is_weak(&self, score: u8) -> bool {
+        score < self.min_score.min(4)
+    }
+}
+
+/// Несекретные сведения о хранилище, которые показываются до его открытия
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultLabel {
+    pub display_name: String,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+/// Настройки проверки паролей по спискам утечек
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BreachCheckSettings {
+    /// Локальный список Pwned Passwords: файл SHA-1 по порядку хешей или
+    /// папка файлов диапазонов; пустая строка — не задан
+    pub hash_file: String,
+    /// Разрешить проверку по сети. Без явного согласия пользователя
+    /// никакие данные о паролях не покидают компьютер.
+    pub online_enabled: bool,
+    /// Адрес сервиса диапазонов или локального зеркала (без `/range`).
+    /// `http://` допускается только для адресов этого компьютера.
+    pub range_api_url: String,
+    /// Просить сервис дополнять ответы фиктивными записями
+    pub padding: bool,
+    /// Сколько часов хранить ответы в памяти (0 — не хранить)
+    pub cache_hours: u32,
+}
+
+impl Default for BreachCheckSettings {
+    fn default() -> Self {
+        Self {
+            hash_file: String::new(),
+            online_enabled: false,
+            range_api_url: DEFAULT_RANGE_API_URL.to_string(),
+            padding: true,
+            cache_hours: 24,
+        }
+    }
+}
+
+/// Настройки встроенного ssh-agent
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SshAgentSettings {
+    /// Путь к сокету; пустая строка — `nopeekpanda-agent/agent.sock` в папке
+    /// времени выполнения пользователя. Каталог сокета должен быть закрыт
+    /// для других пользователей.
+    pub socket_path: String,
+    /// Спрашивать разрешение перед каждым использованием ключа
+    pub confirm_each_use: bool,
+}
+
+impl Default for SshAgentSettings {
+    fn default() -> Self {
+        Self {
+            socket_path: String::new(),
+            confirm_each_use: true,
+        }
+    }
+}
+
+/// Папка с хранилищами или отдельный файл хранилища, добавленные пользователем
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct VaultLocation {
+    pub kind: VaultLocationKind,
+    /// Канонический путь
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultLocationKind {
+    Folder,
+    File,
+}
+
+/// Настройки автоматических резервных копий хранилищ
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// Папка для копий; пустая строка — `backups` внутри папки хранилищ
+    pub directory: String,
+    /// Делать копию при каждом открытии хранилища
+    pub on_unlock: bool,
+    /// Делать копию после стольких изменений (0 — не делать)
+    pub every_changes: u32,
+    /// Сколько последних копий хранить для каждого хранилища
+    pub keep_count: usize,
+    /// Удалять копии старше стольких дней (0 — не удалять по возрасту)
+    pub max_age_days: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: String::new(),
+            on_unlock: true,
+            every_changes: 20,
+            keep_count: 10,
+            max_age_days: 90,
         }
     }
 }
@@ -28,6 +181,59 @@ impl AppSettings {
         PathBuf::from(&self.vault_folder_path)
     }
 
+    /// Пути добавленных мест указанного вида
+    pub fn location_paths(&self, kind: VaultLocationKind) -> Vec<PathBuf> {
+        self.vault_locations
+            .iter()
+            .filter(|location| location.kind == kind)
+            .map(|location| PathBuf::from(&location.path))
+            .collect()
+    }
+
+    /// Переносит время открытия и сведения о хранилище на новый путь файла
+    pub fn move_vault_records(&mut self, from: &str, to: &str) {
+        if let Some(opened) = self.last_opened.remove(from) {
+            self.last_opened.insert(to.to_string(), opened);
+        }
+        if let Some(label) = self.vault_labels.remove(from) {
+            self.vault_labels.insert(to.to_string(), label);
+        }
+    }
+
+    /// Забывает время открытия и сведения об удалённом хранилище
+    pub fn forget_vault_records(&mut self, path: &str) {
+        self.last_opened.remove(path);
+        self.vault_labels.remove(path);
+    }
+
+    pub fn get_backup_path(&self) -> PathBuf {
+        if self.backup.directory.is_empty() {
+            self.get_vault_folder_path().join("backups")
+        } else {
+            PathBuf::from(&self.backup.directory)
+        }
+    }
+
+    pub fn get_ssh_agent_socket_path(&self) -> PathBuf {
+        if !self.ssh_agent.socket_path.is_empty() {
+            return PathBuf::from(&self.ssh_agent.socket_path);
+        }
+
+        // Агент создаёт каталог сокета с правами 0700. Временная папка общая
+        // для всех пользователей, поэтому имя каталога в ней включает uid.
+        let directory = match dirs::runtime_dir() {
+            Some(runtime_dir) => runtime_dir.join("nopeekpanda-agent"),
+            #[cfg(unix)]
+            None => std::env::temp_dir().join(format!(
+                "nopeekpanda-agent-{}",
+                crate::utils::fs::current_uid()
+            )),
+            #[cfg(not(unix))]
+            None => std::env::temp_dir().join("nopeekpanda-agent"),
+        };
+        directory.join("agent.sock")
+    }
+
     fn get_config_path() -> Result<PathBuf> {
         let proj_dirs = ProjectDirs::from("ru", "CEBikol", "nopeekpanda")
             .context("Failed to determine project directories (OS-specific paths)")?;
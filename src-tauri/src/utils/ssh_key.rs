@@ -0,0 +1,51 @@
+use anyhow::{Context, Result};
+use ssh_key::rand_core::OsRng;
+use ssh_key::{Algorithm, HashAlg, LineEnding, PrivateKey};
+
+use crate::db::operations::SshKeyDetails;
+
+/// Создаёт новую пару ключей Ed25519
+pub fn generate_ed25519(comment: &str) -> Result<SshKeyDetails> {
+    let mut key =
+        PrivateKey::random(&mut OsRng, Algorithm::Ed25519).context("Failed to generate SSH key")?;
+    key.set_comment(comment.trim());
+
+    details(&key)
+}
+
+/// Разбирает закрытый ключ OpenSSH и вычисляет открытый ключ и отпечаток.
+/// `comment` заменяет комментарий, сохранённый в самом ключе.
+pub fn from_private_key(private_key: &str, comment: Option<&str>) -> Result<SshKeyDetails> {
+    let mut key = PrivateKey::from_openssh(private_key.trim())
+        .context("Private key must be in OpenSSH format")?;
+    if key.is_encrypted() {
+        anyhow::bail!("Remove the passphrase from the SSH key before adding it to the vault");
+    }
+    if let Some(comment) = comment {
+        key.set_comment(comment.trim());
+    }
+
+    details(&key)
+}
+
+/// Разбирает сохранённый в хранилище закрытый ключ
+pub fn parse_private_key(private_key: &str) -> Result<PrivateKey> {
+    PrivateKey::from_openssh(private_key).context("Failed to parse stored SSH key")
+}
+
+fn details(key: &PrivateKey) -> Result<SshKeyDetails> {
+    let private_key = key
+        .to_openssh(LineEnding::LF)
+        .context("Failed to encode SSH key")?;
+    let public_key = key
+        .public_key()
+        .to_openssh()
+        .context("Failed to encode SSH public key")?;
+
+    Ok(SshKeyDetails {
+        private_key: private_key.to_string(),
+        public_key,
+        fingerprint: key.fingerprint(HashAlg::Sha256).to_string(),
+        comment: key.comment().to_string(),
+    })
+}
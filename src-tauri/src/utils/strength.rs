@@ -0,0 +1,143 @@
+use anyhow::Result;
+use serde::Serialize;
+use zxcvbn::time_estimates::CrackTimeSeconds;
+use zxcvbn::zxcvbn;
+
+use super::settings::MasterPasswordSettings;
+
+/// Оценка стойкости пароля (алгоритм zxcvbn)
+#[derive(Debug, Clone, Serialize)]
+pub struct StrengthReport {
+    /// От 0 (подбирается мгновенно) до 4 (очень стойкий)
+    pub score: u8,
+    /// Ожидаемое число попыток подбора
+    pub guesses: u64,
+    pub guesses_log10: f64,
+    /// Время подбора офлайн при медленном хеше (10⁴ попыток в секунду), секунды
+    pub crack_time_seconds: f64,
+    /// То же время в читаемом виде («3 hours», «centuries»)
+    pub crack_time_display: String,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+/// Оценивает пароль с учётом слов, которые легко угадать
+/// (имя хранилища, логин, адрес сайта)
+pub fn estimate(password: &str, user_inputs: &[&str]) -> StrengthReport {
+    let entropy = zxcvbn(password, user_inputs);
+    let crack_time = entropy.crack_times().offline_slow_hashing_1e4_per_second();
+    let feedback = entropy.feedback();
+
+    StrengthReport {
+        score: entropy.score().into(),
+        guesses: entropy.guesses(),
+        guesses_log10: entropy.guesses_log10().max(0.0),
+        crack_time_seconds: match crack_time {
+            CrackTimeSeconds::Integer(seconds) => seconds as f64,
+            CrackTimeSeconds::Float(seconds) => seconds,
+        },
+        crack_time_display: crack_time.to_string(),
+        warning: feedback
+            .and_then(|feedback| feedback.warning())
+            .map(|warning| warning.to_string()),
+        suggestions: feedback
+            .map(|feedback| {
+                feedback
+                    .suggestions()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Проверяет мастер-пароль нового хранилища по требованиям из настроек.
+/// Слабый пароль возвращается оценкой для предупреждения, а если слабые
+/// пароли запрещены — ошибкой; стойкий пароль даёт `None`.
+pub fn check_master_password(
+    password: &str,
+    vault_name: &str,
+    requirements: &MasterPasswordSettings,
+) -> Result<Option<StrengthReport>> {
+    let report = estimate(password, &[vault_name]);
+    if !requirements.is_weak(report.score) {
+        return Ok(None);
+    }
+    if requirements.block_weak {
+        anyhow::bail!(
+            "Master password is too weak: it can be cracked in {}",
+            report.crack_time_display
+        );
+    }
+
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "correct-Horse-battery-staple-91-lantern";
+
+    #[test]
+    fn short_common_password_scores_zero_with_feedback() {
+        let report = estimate("123", &[]);
+
+        assert_eq!(report.score, 0);
+        assert!(report.guesses < 1_000);
+        assert!(report.crack_time_seconds < 1.0);
+        assert!(report.warning.is_some() || !report.suggestions.is_empty());
+    }
+
+    #[test]
+    fn long_passphrase_scores_four() {
+        let report = estimate(PASSPHRASE, &[]);
+
+        assert_eq!(report.score, 4);
+        assert!(report.guesses_log10 > 10.0);
+        assert!(report.crack_time_display.contains("centuries"));
+        assert!(report.warning.is_none());
+    }
+
+    #[test]
+    fn user_inputs_lower_the_score() {
+        let password = "Brumbleflax93";
+        let alone = estimate(password, &[]);
+        let with_inputs = estimate(password, &["brumbleflax"]);
+
+        assert!(with_inputs.score < alone.score);
+        assert!(with_inputs.guesses < alone.guesses);
+    }
+
+    #[test]
+    fn weak_master_password_is_warned_or_blocked() {
+        let mut requirements = MasterPasswordSettings::default();
+
+        let warning = check_master_password("qwerty123", "Рабочее", &requirements).unwrap();
+        assert!(warning.is_some_and(|report| report.score < requirements.min_score));
+        assert!(check_master_password(PASSPHRASE, "Рабочее", &requirements)
+            .unwrap()
+            .is_none());
+
+        requirements.block_weak = true;
+        let error = check_master_password("qwerty123", "Рабочее", &requirements).unwrap_err();
+        assert!(error.to_string().starts_with("Master password is too weak"));
+        assert!(check_master_password(PASSPHRASE, "Рабочее", &requirements)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn vault_name_counts_against_master_password() {
+        let requirements = MasterPasswordSettings {
+            min_score: 4,
+            block_weak: true,
+        };
+
+        assert!(
+            check_master_password("Семейное-хранилище", "Семейное-хранилище", &requirements)
+                .is_err()
+        );
+    }
+}